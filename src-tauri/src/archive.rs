@@ -0,0 +1,371 @@
+// Archive Extraction Module
+// Unpacks downloaded runtimes into a private staging directory and swaps them into place in one rename
+
+use std::fs::File;
+use std::path::{Component, Path, PathBuf};
+use flate2::read::GzDecoder;
+use rand::distributions::{Alphanumeric, DistString};
+use tar::{Archive, EntryType};
+use tracing::{info, warn};
+
+/// A directory that is removed again when it goes out of scope
+pub struct Staging {
+    path: PathBuf,
+}
+
+impl Staging {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Unpack a .tar.gz into a fresh staging directory inside `parent`.
+/// Entries that would land outside the staging directory, through `..`, an absolute path
+/// or a link, are rejected, as is an archive that unpacks to more than `max_bytes`.
+pub fn extract_tar_gz(archive_path: &Path, parent: &Path, name: &str, max_bytes: u64) -> Result<Staging, String> {
+    std::fs::create_dir_all(parent)
+        .map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+    remove_stale_staging(parent, name);
+
+    // A unique name, so a directory left behind by a crash is never mistaken for this run's
+    let suffix = Alphanumeric.sample_string(&mut rand::thread_rng(), 8);
+    let staging = Staging { path: parent.join(format!(".{}-extract-{}", name, suffix)) };
+    std::fs::create_dir(staging.path())
+        .map_err(|e| format!("Failed to create staging directory: {}", e))?;
+
+    let file = File::open(archive_path)
+        .map_err(|e| format!("Failed to open archive: {}", e))?;
+    let mut archive = Archive::new(GzDecoder::new(file));
+    archive.set_preserve_permissions(true);
+    archive.set_overwrite(false);
+
+    let mut unpacked = 0u64;
+    let entries = archive.entries()
+        .map_err(|e| format!("Failed to read archive: {}", e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read archive: {}", e))?;
+        let path = entry.path()
+            .map_err(|e| format!("Invalid path in archive: {}", e))?
+            .into_owned();
+
+        if !is_plain_relative(&path) {
+            return Err(format!("Archive entry {:?} escapes the extraction directory", path));
+        }
+
+        match entry.header().entry_type() {
+            EntryType::Regular | EntryType::Directory | EntryType::Continuous => {}
+            EntryType::Symlink | EntryType::Link => {
+                let target = entry.link_name()
+                    .map_err(|e| format!("Invalid link in archive: {}", e))?
+                    .ok_or_else(|| format!("Link {:?} has no target", path))?;
+                // Symlink targets are relative to the link's directory, hard links to the archive root
+                let resolved = if entry.header().entry_type() == EntryType::Symlink {
+                    path.parent().unwrap_or(Path::new("")).join(&target)
+                } else {
+                    target.to_path_buf()
+                };
+                if !is_contained(&resolved) {
+                    return Err(format!("Link {:?} -> {:?} points outside the extraction directory", path, target));
+                }
+            }
+            // Long names and pax headers are handled by the tar reader itself
+            EntryType::GNULongName | EntryType::GNULongLink | EntryType::XHeader | EntryType::XGlobalHeader => {}
+            other => {
+                warn!("Skipping unsupported archive entry {:?} ({:?})", path, other);
+                continue;
+            }
+        }
+
+        unpacked = unpacked.saturating_add(entry.header().size().unwrap_or(0));
+        if unpacked > max_bytes {
+            return Err(format!(
+                "Archive unpacks to more than {} MB, refusing to extract it",
+                max_bytes / (1024 * 1024)
+            ));
+        }
+
+        entry.unpack_in(staging.path())
+            .map_err(|e| format!("Failed to extract {:?}: {}", path, e))?;
+    }
+
+    // Links can still escape in combination (a link through another link), so check where they really lead
+    let root = staging.path().canonicalize()
+        .map_err(|e| format!("Failed to resolve staging directory: {}", e))?;
+    check_links(&root, &root)?;
+
+    info!("Extracted {:?} ({} MB)", archive_path, unpacked / (1024 * 1024));
+    Ok(staging)
+}
+
+/// Move `source` to `target`, replacing whatever is there. The new directory appears in a
+/// single rename, so a half-installed runtime is never visible under `target`. A crash
+/// between moving the old one aside and moving the new one in is undone by
+/// `restore_interrupted_replace`.
+pub fn replace_dir(source: &Path, target: &Path) -> Result<(), String> {
+    let previous = target.exists().then(|| {
+        let suffix = Alphanumeric.sample_string(&mut rand::thread_rng(), 8);
+        let name = target.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        target.with_file_name(format!(".{}-old-{}", name, suffix))
+    });
+
+    if let Some(previous) = &previous {
+        std::fs::rename(target, previous)
+            .map_err(|e| format!("Failed to move {:?} aside: {}", target, e))?;
+    }
+
+    if let Err(e) = std::fs::rename(source, target) {
+        // Put the old installation back rather than leaving nothing
+        if let Some(previous) = &previous {
+            let _ = std::fs::rename(previous, target);
+        }
+        return Err(format!("Failed to move {:?} into place: {}", target, e));
+    }
+
+    if let Some(previous) = previous {
+        let _ = std::fs::remove_dir_all(previous);
+    }
+    Ok(())
+}
+
+/// Put the previous installation back if `replace_dir` was interrupted after moving it aside
+pub fn restore_interrupted_replace(target: &Path) {
+    if target.exists() {
+        return;
+    }
+    let (Some(parent), Some(name)) = (target.parent(), target.file_name()) else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(parent) else {
+        return;
+    };
+    let prefix = format!(".{}-old-", name.to_string_lossy());
+    let previous = entries
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with(&prefix))
+        .max_by_key(|e| e.metadata().and_then(|m| m.modified()).ok());
+    if let Some(previous) = previous {
+        match std::fs::rename(previous.path(), target) {
+            Ok(()) => info!("Restored {:?} from an interrupted update", target),
+            Err(e) => warn!("Failed to restore {:?} from {:?}: {}", target, previous.path(), e),
+        }
+    }
+}
+
+/// A relative path without `..`, so it cannot leave its root lexically
+fn is_plain_relative(path: &Path) -> bool {
+    path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Fail if any symlink below `dir` resolves to a location outside `root`
+fn check_links(root: &Path, dir: &Path) -> Result<(), String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {:?}: {}", dir, e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_symlink() {
+            // A dangling link is judged by where it would point
+            let resolved = match path.canonicalize() {
+                Ok(resolved) => resolved,
+                Err(_) => {
+                    let target = std::fs::read_link(&path)
+                        .map_err(|e| format!("Failed to read link {:?}: {}", path, e))?;
+                    let base = dir.strip_prefix(root).unwrap_or(Path::new("")).join(&target);
+                    if !is_contained(&base) {
+                        return Err(format!("Link {:?} points outside the extraction directory", path));
+                    }
+                    continue;
+                }
+            };
+            if !resolved.starts_with(root) {
+                return Err(format!("Link {:?} points outside the extraction directory", path));
+            }
+        } else if file_type.is_dir() {
+            check_links(root, &path)?;
+        }
+    }
+    Ok(())
+}
+
+/// A relative path that stays inside its root, even after resolving `..`
+fn is_contained(path: &Path) -> bool {
+    let mut depth = 0usize;
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if depth == 0 {
+                    return false;
+                }
+                depth -= 1;
+            }
+            Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+    true
+}
+
+/// Remove staging and replaced directories an interrupted install left in `parent`.
+/// A replaced directory is only removed once the installation it was replaced by exists.
+fn remove_stale_staging(parent: &Path, name: &str) {
+    restore_interrupted_replace(&parent.join(name));
+    let Ok(entries) = std::fs::read_dir(parent) else {
+        return;
+    };
+    let prefixes = [
+        format!(".{}-extract-", name),
+        format!(".{}-old-", name),
+        // Fixed name used by earlier versions
+        format!("{}-extract-temp", name),
+    ];
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if prefixes.iter().any(|p| file_name.starts_with(p.as_str())) {
+            info!("Removing leftover {:?}", entry.path());
+            let _ = std::fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use tar::{Builder, Header};
+
+    /// Type, path, link target and contents of one archive entry
+    type Entry<'a> = (EntryType, &'a str, &'a str, &'a [u8]);
+
+    /// A fresh directory for one test, next to which nothing else is written
+    fn test_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("booklore-archive-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("install")).unwrap();
+        dir
+    }
+
+    /// Write the entries as a .tar.gz. Names are written into the header as is,
+    /// since tar::Builder refuses the hostile paths these tests need.
+    fn write_archive(dir: &Path, entries: &[Entry]) -> PathBuf {
+        let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+        for (kind, path, link, data) in entries {
+            let mut header = Header::new_old();
+            header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+            header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+            header.set_entry_type(*kind);
+            header.set_mode(if *kind == EntryType::Directory { 0o755 } else { 0o644 });
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append(&header, *data).unwrap();
+        }
+        let bytes = builder.into_inner().unwrap().finish().unwrap();
+        let path = dir.join("runtime.tar.gz");
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    /// Extract into `<dir>/install`, as the runtimes are, and check no staging directory is left on failure
+    fn extract(dir: &Path, entries: &[Entry], max_bytes: u64) -> Result<Staging, String> {
+        let archive = write_archive(dir, entries);
+        let result = extract_tar_gz(&archive, &dir.join("install"), "jre", max_bytes);
+        if result.is_err() {
+            assert_eq!(std::fs::read_dir(dir.join("install")).unwrap().count(), 0);
+        }
+        result
+    }
+
+    #[test]
+    fn extracts_a_plain_archive() {
+        let dir = test_dir("plain");
+        let staging = extract(&dir, &[
+            (EntryType::Directory, "jdk/", "", b""),
+            (EntryType::Regular, "jdk/release", "", b"JAVA_VERSION=21"),
+            (EntryType::Symlink, "jdk/current", "release", b""),
+        ], 1024).unwrap();
+        assert_eq!(std::fs::read(staging.path().join("jdk/current")).unwrap(), b"JAVA_VERSION=21");
+    }
+
+    #[test]
+    fn rejects_parent_dir_entries() {
+        let dir = test_dir("dotdot");
+        let result = extract(&dir, &[(EntryType::Regular, "../evil", "", b"x")], 1024);
+        assert!(result.is_err_and(|e| e.contains("escapes")));
+        assert!(!dir.join("evil").exists());
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        let dir = test_dir("absolute");
+        let target = format!("{}/evil", dir.display());
+        let result = extract(&dir, &[(EntryType::Regular, &target, "", b"x")], 1024);
+        assert!(result.is_err_and(|e| e.contains("escapes")));
+        assert!(!dir.join("evil").exists());
+    }
+
+    #[test]
+    fn rejects_symlinks_out_of_the_archive() {
+        let dir = test_dir("symlink");
+        let result = extract(&dir, &[(EntryType::Symlink, "jdk", "../..", b"")], 1024);
+        assert!(result.is_err_and(|e| e.contains("points outside")));
+    }
+
+    #[test]
+    fn rejects_a_symlink_through_another_symlink() {
+        // Each link stays inside on paper, but sub/parent is the root, so escape is its parent
+        let dir = test_dir("chain");
+        let result = extract(&dir, &[
+            (EntryType::Directory, "sub/", "", b""),
+            (EntryType::Symlink, "sub/parent", "..", b""),
+            (EntryType::Symlink, "escape", "sub/parent/..", b""),
+        ], 1024);
+        assert!(result.is_err_and(|e| e.contains("points outside")));
+    }
+
+    #[test]
+    fn rejects_hard_links_out_of_the_archive() {
+        let dir = test_dir("hardlink");
+        std::fs::write(dir.join("secret"), b"x").unwrap();
+        for target in ["../secret", "/etc/passwd"] {
+            let result = extract(&dir, &[(EntryType::Link, "jdk", target, b"")], 1024);
+            assert!(result.is_err_and(|e| e.contains("points outside")), "{}", target);
+        }
+    }
+
+    #[test]
+    fn rejects_archives_over_the_size_cap() {
+        let dir = test_dir("size");
+        let result = extract(&dir, &[
+            (EntryType::Regular, "a", "", &[0; 600]),
+            (EntryType::Regular, "b", "", &[0; 600]),
+        ], 1024);
+        assert!(result.is_err_and(|e| e.contains("more than")));
+    }
+
+    #[test]
+    fn restores_a_runtime_moved_aside_by_an_interrupted_replace() {
+        let dir = test_dir("restore");
+        let old = dir.join("install/.jre-old-abcdefgh");
+        std::fs::create_dir(&old).unwrap();
+        std::fs::write(old.join("release"), b"old").unwrap();
+
+        remove_stale_staging(&dir.join("install"), "jre");
+        assert_eq!(std::fs::read(dir.join("install/jre/release")).unwrap(), b"old");
+        assert!(!old.exists());
+
+        // Once the runtime is in place, a replaced directory is only leftover
+        std::fs::create_dir(&old).unwrap();
+        remove_stale_staging(&dir.join("install"), "jre");
+        assert!(!old.exists());
+        assert!(dir.join("install/jre/release").exists());
+    }
+}
@@ -1,18 +1,81 @@
 // Spring Boot Backend Management Module
 // Handles launching and monitoring the BookLore Java backend
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
-use std::sync::OnceLock;
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
 use tauri::{AppHandle, Manager};
-use tokio::sync::Mutex;
-use tracing::info;
+use tauri_plugin_notification::NotificationExt;
+use tracing::{info, warn};
 
-// Store the backend process handle
-static BACKEND_PROCESS: OnceLock<Mutex<Option<Child>>> = OnceLock::new();
+use crate::config::RunnerConfig;
+use crate::logs::LogSink;
+use crate::service::{RestartPolicy, Service, ServiceHandle};
 
-fn get_process_mutex() -> &'static Mutex<Option<Child>> {
-    BACKEND_PROCESS.get_or_init(|| Mutex::new(None))
+/// The BookLore Spring Boot backend as a supervised service
+pub struct BackendService {
+    java_path: String,
+    port: u16,
+    config: RunnerConfig,
+    logs: Arc<LogSink>,
+}
+
+impl BackendService {
+    pub fn new(java_path: &str, config: &RunnerConfig, logs: Arc<LogSink>) -> Self {
+        Self {
+            java_path: java_path.to_string(),
+            port: config.ports.backend,
+            config: config.clone(),
+            logs,
+        }
+    }
+}
+
+#[async_trait]
+impl Service for BackendService {
+    fn name(&self) -> &'static str {
+        "backend"
+    }
+
+    fn label(&self) -> &'static str {
+        "Backend"
+    }
+
+    async fn spawn(&self, app: &AppHandle) -> Result<ServiceHandle, String> {
+        self.logs.push(&format!("--- Starting BookLore backend on port {} ---", self.port));
+        let mut child = spawn_backend(app, &self.java_path, &self.config)?;
+
+        // Drain both pipes so the JVM never blocks on a full buffer
+        if let Some(stdout) = child.stdout.take() {
+            self.logs.capture(stdout);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            self.logs.capture(stderr);
+        }
+
+        Ok(ServiceHandle::Process(child))
+    }
+
+    async fn wait_ready(&self, _app: &AppHandle) -> Result<(), String> {
+        wait_for_backend(self.port).await?;
+        info!("Backend is ready on port {}", self.port);
+        Ok(())
+    }
+
+    fn stop_timeout(&self) -> Duration {
+        Duration::from_secs(self.config.backend.stop_timeout_secs)
+    }
+
+    async fn health_check(&self) -> bool {
+        is_healthy(self.port).await
+    }
+
+    fn restart_policy(&self) -> Option<RestartPolicy> {
+        // The JVM can die from OOM at -Xmx512m; bring it back instead of serving 502s
+        Some(RestartPolicy::default())
+    }
 }
 
 /// Get the BookLore JAR path
@@ -31,6 +94,107 @@ fn get_jar_path(app: &AppHandle) -> PathBuf {
     }
 }
 
+/// Host for a JDBC URL: IPv6 addresses go in brackets
+fn jdbc_host(host: &str) -> String {
+    let host = host.trim().trim_start_matches('[').trim_end_matches(']');
+    if host.contains(':') {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+/// Whether the Spring Boot JAR bundles JNA (BOOT-INF/lib/jna-*.jar), read from the zip
+/// central directory at the end of the file. Anything the reader does not fully
+/// understand (zip64, a truncated or inconsistent directory) is an error, never a guess.
+fn jar_has_jna(jar: &Path) -> Result<bool, String> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let read_error = |e: std::io::Error| format!("Failed to read {:?}: {}", jar, e);
+    let invalid = || format!("Cannot read the contents of {:?}", jar);
+    let mut file = std::fs::File::open(jar).map_err(read_error)?;
+    let len = file.metadata().map_err(read_error)?.len();
+
+    // The end-of-central-directory record is within the last 64 KiB + 22 bytes
+    let tail_len = len.min(65_557);
+    file.seek(SeekFrom::End(-(tail_len as i64))).map_err(read_error)?;
+    let mut tail = vec![0u8; tail_len as usize];
+    file.read_exact(&mut tail).map_err(read_error)?;
+
+    let record_pos = tail.windows(4)
+        .rposition(|w| w == [0x50, 0x4b, 0x05, 0x06])
+        .ok_or_else(invalid)?;
+    let record = &tail[record_pos..];
+    let u16_at = |bytes: &[u8], at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+    let u32_at = |bytes: &[u8], at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+    // The record and its comment run to the end of the file
+    if record.len() < 22 || record.len() != 22 + u16_at(record, 20) as usize {
+        return Err(invalid());
+    }
+    let entries = u16_at(record, 10);
+    let size = u32_at(record, 12);
+    let offset = u32_at(record, 16);
+    // All-ones fields mean the real values are in a zip64 record
+    if entries == u16::MAX || size == u32::MAX || offset == u32::MAX {
+        return Err(invalid());
+    }
+    // The directory ends right where the record starts
+    if offset as u64 + size as u64 != len - tail_len + record_pos as u64 {
+        return Err(invalid());
+    }
+
+    file.seek(SeekFrom::Start(offset as u64)).map_err(read_error)?;
+    let mut directory = vec![0u8; size as usize];
+    file.read_exact(&mut directory).map_err(read_error)?;
+
+    // Walk the file headers rather than searching the bytes, so a comment cannot match
+    let mut pos = 0;
+    let mut found = false;
+    for _ in 0..entries {
+        let header = directory.get(pos..pos + 46)
+            .filter(|header| header[..4] == [0x50, 0x4b, 0x01, 0x02])
+            .ok_or_else(invalid)?;
+        let name_len = u16_at(header, 28) as usize;
+        let extra_len = u16_at(header, 30) as usize;
+        let comment_len = u16_at(header, 32) as usize;
+        let name = directory.get(pos + 46..pos + 46 + name_len).ok_or_else(invalid)?;
+        let name = String::from_utf8_lossy(name);
+        if let Some(file_name) = name.strip_prefix("BOOT-INF/lib/") {
+            found |= file_name.starts_with("jna-") && !file_name.starts_with("jna-platform") && file_name.ends_with(".jar");
+        }
+        pos += 46 + name_len + extra_len + comment_len;
+    }
+    if pos != directory.len() {
+        return Err(invalid());
+    }
+    Ok(found)
+}
+
+/// Settings for one launch. Socket-only mode needs JNA in the backend; unless the JAR
+/// certainly bundles it, the database keeps listening on TCP rather than being unreachable.
+/// That goes against the user's setting, so it is shown in the startup status and a notification.
+pub async fn with_transport_fallback(app: &AppHandle, mut config: RunnerConfig) -> RunnerConfig {
+    if !config.mariadb.socket_only || config.external_database.enabled {
+        return config;
+    }
+    let jar = get_jar_path(app);
+    let reason = match tokio::task::spawn_blocking(move || jar_has_jna(&jar)).await {
+        Ok(Ok(true)) => return config,
+        Ok(Ok(false)) => "the BookLore backend does not include JNA".to_string(),
+        Ok(Err(e)) => e,
+        Err(e) => format!("checking the BookLore backend failed: {}", e),
+    };
+
+    let message = format!("Socket-only mode is unavailable ({}). The database listens on 127.0.0.1 instead.", reason);
+    warn!("{}", message);
+    crate::emit_status(app, "mariadb", "active", &message, 10);
+    if let Err(e) = app.notification().builder().title("Database reachable over TCP").body(&message).show() {
+        warn!("Failed to show notification: {}", e);
+    }
+    config.mariadb.socket_only = false;
+    config
+}
+
 /// Get the frontend dist path
 #[allow(dead_code)]
 fn get_frontend_path(app: &AppHandle) -> PathBuf {
@@ -45,27 +209,18 @@ fn get_frontend_path(app: &AppHandle) -> PathBuf {
     }
 }
 
-/// Get the books directory
-fn get_books_dir() -> PathBuf {
-    crate::get_app_data_dir().join("books")
-}
-
-/// Get the BookDrop directory
-fn get_bookdrop_dir() -> PathBuf {
-    crate::get_app_data_dir().join("bookdrop")
+/// Create the sink that receives backend stdout/stderr
+pub fn create_log_sink() -> LogSink {
+    LogSink::new(
+        crate::get_app_data_dir().join("backend.log"),
+        crate::constants::LOG_MAX_BYTES,
+        crate::constants::LOG_KEEP_FILES,
+        crate::constants::LOG_BUFFER_LINES,
+    )
 }
 
-/// Start the BookLore Spring Boot backend
-pub async fn start(app: &AppHandle, java_path: &str, port: u16) -> Result<(), String> {
-    // Check if already running
-    {
-        let guard = get_process_mutex().lock().await;
-        if guard.is_some() {
-            info!("Backend already running");
-            return Ok(());
-        }
-    }
-    
+/// Launch the BookLore Spring Boot backend process
+fn spawn_backend(app: &AppHandle, java_path: &str, config: &RunnerConfig) -> Result<Child, String> {
     let jar_path = get_jar_path(app);
     
     if !jar_path.exists() {
@@ -77,81 +232,65 @@ pub async fn start(app: &AppHandle, java_path: &str, port: u16) -> Result<(), St
     // Create necessary directories
     let app_data_dir = crate::get_app_data_dir();
     let config_dir = app_data_dir.join("config");
-    let books_dir = get_books_dir();
-    let bookdrop_dir = get_bookdrop_dir();
+    let books_dir = &config.paths.books_dir;
+    let bookdrop_dir = &config.paths.bookdrop_dir;
+    let port = config.ports.backend;
     
     std::fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
-    std::fs::create_dir_all(&books_dir).map_err(|e| e.to_string())?;
-    std::fs::create_dir_all(&bookdrop_dir).map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(books_dir).map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(bookdrop_dir).map_err(|e| e.to_string())?;
     
-    // Build database URL - use TCP connection to localhost
-    // Build database URL - use TCP connection to localhost
-    let database_url = format!("jdbc:mariadb://127.0.0.1:{}/booklore?createDatabaseIfNotExist=true", crate::constants::MARIADB_PORT);
+    // Build database URL - the external server, TCP to localhost, or the Unix socket when the database has no port
+    let external = &config.external_database;
+    let (database_url, username, password) = if external.enabled {
+        let url = format!(
+            "jdbc:mariadb://{}:{}/{}?createDatabaseIfNotExist=true",
+            jdbc_host(&external.host), external.port, external.schema
+        );
+        (url, external.user.clone(), external.password.clone())
+    } else {
+        let url = match crate::mariadb::DbAddress::from_config(config) {
+            crate::mariadb::DbAddress::Tcp(port) => {
+                format!("jdbc:mariadb://127.0.0.1:{}/booklore?createDatabaseIfNotExist=true", port)
+            }
+            crate::mariadb::DbAddress::Socket(socket) => {
+                // Connector/J opens Unix sockets through JNA, checked by with_transport_fallback()
+                format!("jdbc:mariadb://localhost/booklore?localSocket={}&createDatabaseIfNotExist=true", socket.display())
+            }
+        };
+        
+        // Accounts are set up by the MariaDB service before the backend starts
+        let credentials = crate::credentials::load()?
+            .filter(|c| c.provisioned)
+            .ok_or("Database credentials have not been set up yet")?;
+        (url, crate::credentials::DB_USER.to_string(), credentials.password)
+    };
     
     // Get JAVA_HOME
     let java_home = crate::jre::get_java_home();
     
     // Build the command
-    let child = Command::new(java_path)
+    let mut command = Command::new(java_path);
+    command
         .env("JAVA_HOME", &java_home)
         .env("DATABASE_URL", &database_url)
-        .env("DATABASE_USERNAME", "root")
-        .env("DATABASE_PASSWORD", "")
+        .env("DATABASE_USERNAME", &username)
+        .env("DATABASE_PASSWORD", &password)
         .env("BOOKLORE_PORT", port.to_string())
-        .arg("-Xmx512m")  // Limit heap size
-        .arg("-Xms128m")
+        .arg(format!("-Xmx{}m", config.backend.heap_max_mb))  // Limit heap size
+        .arg(format!("-Xms{}m", config.backend.heap_min_mb))
         .arg(format!("-Dapp.path-config={}", config_dir.display()))
         .arg(format!("-Dapp.bookdrop-folder={}", bookdrop_dir.display()))
         .arg(format!("-Dserver.port={}", port))
+        .arg(format!("-Dspring.datasource.hikari.maximum-pool-size={}", crate::constants::BACKEND_DB_POOL_SIZE))
         .arg("-jar")
         .arg(&jar_path)
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
+        .stderr(Stdio::piped());
+    crate::process::bind_to_runner(&mut command);
+    command
         .spawn()
-        .map_err(|e| format!("Failed to start backend: {}", e))?;
-    
-    info!("Backend started with PID: {}", child.id());
-    
-    // Store process handle
-    {
-        let mut guard = get_process_mutex().lock().await;
-        *guard = Some(child);
-    }
-    
-    // Wait for health check
-    wait_for_backend(port).await?;
-    
-    info!("Backend is ready on port {}", port);
-    Ok(())
-}
-
-/// Stop the backend process
-pub async fn stop() -> Result<(), String> {
-    let mut guard = get_process_mutex().lock().await;
-    
-    if let Some(mut child) = guard.take() {
-        info!("Stopping backend...");
-        
-        // Send SIGTERM for graceful shutdown
-        #[cfg(unix)]
-        {
-            
-            unsafe {
-                libc::kill(child.id() as i32, libc::SIGTERM);
-            }
-        }
-        
-        // Wait for graceful shutdown
-        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-        
-        // Force kill if still running
-        let _ = child.kill();
-        let _ = child.wait();
-        
-        info!("Backend stopped");
-    }
-    
-    Ok(())
+        .map_err(|e| format!("Failed to start backend: {}", e))
 }
 
 /// Wait for backend to be ready
@@ -180,13 +319,116 @@ async fn wait_for_backend(port: u16) -> Result<(), String> {
 }
 
 /// Check if backend is healthy
-#[allow(dead_code)]
 pub async fn is_healthy(port: u16) -> bool {
     let health_url = format!("http://localhost:{}/api/v1/healthcheck", port);
-    let client = reqwest::Client::new();
+    // A hung JVM must count as unhealthy rather than block the watchdog
+    let client = match reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+    {
+        Ok(client) => client,
+        Err(_) => return false,
+    };
     
     match client.get(&health_url).send().await {
         Ok(response) => response.status().is_success(),
         Err(_) => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A zip holding only a central directory with the given entry names, and an archive comment
+    fn jar(test: &str, names: &[&str], comment: &str) -> PathBuf {
+        let mut directory = Vec::new();
+        for name in names {
+            let mut header = vec![0u8; 46];
+            header[..4].copy_from_slice(&[0x50, 0x4b, 0x01, 0x02]);
+            header[28..30].copy_from_slice(&(name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&header);
+            directory.extend_from_slice(name.as_bytes());
+        }
+
+        let mut bytes = b"local file data".to_vec();
+        let offset = bytes.len() as u32;
+        bytes.extend_from_slice(&directory);
+        bytes.extend_from_slice(&[0x50, 0x4b, 0x05, 0x06, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&(names.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&(names.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&offset.to_le_bytes());
+        bytes.extend_from_slice(&(comment.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(comment.as_bytes());
+
+        let path = std::env::temp_dir().join(format!("booklore-jar-{}-{}.jar", test, std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn finds_a_bundled_jna() {
+        let path = jar("jna", &["BOOT-INF/lib/slf4j-api-2.0.9.jar", "BOOT-INF/lib/jna-5.14.0.jar"], "");
+        assert_eq!(jar_has_jna(&path), Ok(true));
+    }
+
+    #[test]
+    fn does_not_match_outside_the_entry_names() {
+        let path = jar("no-jna", &["BOOT-INF/lib/jna-platform-5.14.0.jar", "BOOT-INF/classes/jna-notes.txt"], "BOOT-INF/lib/jna-5.jar");
+        assert_eq!(jar_has_jna(&path), Ok(false));
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_read() {
+        let path = jar("truncated", &["BOOT-INF/lib/jna-5.14.0.jar"], "");
+        let mut bytes = std::fs::read(&path).unwrap();
+        // Claim a second entry that is not there
+        let count = bytes.len() - 12;
+        bytes[count..count + 2].copy_from_slice(&2u16.to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(jar_has_jna(&path).is_err());
+
+        let zip64 = jar("zip64", &["BOOT-INF/lib/jna-5.14.0.jar"], "");
+        let mut bytes = std::fs::read(&zip64).unwrap();
+        let offset = bytes.len() - 6;
+        bytes[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&zip64, &bytes).unwrap();
+        assert!(jar_has_jna(&zip64).is_err());
+
+        let entries64 = jar("entries64", &["BOOT-INF/lib/jna-5.14.0.jar"], "");
+        let mut bytes = std::fs::read(&entries64).unwrap();
+        let offset = bytes.len() - 12;
+        bytes[offset..offset + 2].copy_from_slice(&u16::MAX.to_le_bytes());
+        std::fs::write(&entries64, &bytes).unwrap();
+        assert!(jar_has_jna(&entries64).is_err());
+
+        assert!(jar_has_jna(Path::new("/nonexistent/booklore-api.jar")).is_err());
+    }
+
+    #[test]
+    fn rejects_a_truncated_or_foreign_file() {
+        let path = jar("cut", &["BOOT-INF/lib/jna-5.14.0.jar"], "");
+        let bytes = std::fs::read(&path).unwrap();
+        // Cut inside the end record, and inside the central directory
+        for keep in [bytes.len() - 5, bytes.len() - 30, 0] {
+            std::fs::write(&path, &bytes[..keep]).unwrap();
+            assert!(jar_has_jna(&path).is_err(), "{} bytes", keep);
+        }
+
+        std::fs::write(&path, "#!/bin/sh\necho not a jar\n").unwrap();
+        assert!(jar_has_jna(&path).is_err());
+    }
+
+    #[test]
+    fn reads_past_an_archive_comment() {
+        let comment = "x".repeat(1000);
+        let path = jar("comment", &["BOOT-INF/lib/jna-5.14.0.jar"], &comment);
+        assert_eq!(jar_has_jna(&path), Ok(true));
+
+        // A comment ending in what looks like an empty directory must not hide the real one
+        let fake = String::from_utf8([b"PK\x05\x06".as_slice(), &[0u8; 18]].concat()).unwrap();
+        let path = jar("fake-record", &["BOOT-INF/lib/jna-5.14.0.jar"], &fake);
+        assert_ne!(jar_has_jna(&path), Ok(false));
+    }
+}
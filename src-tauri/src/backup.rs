@@ -0,0 +1,726 @@
+// Database Backup Module
+// Dumps the booklore schema into compressed, timestamped files, prunes old ones and restores them
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use chrono::{Datelike, Local, NaiveDateTime};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tauri::{AppHandle, Manager};
+use tokio::sync::OwnedMutexGuard;
+use tauri_plugin_notification::NotificationExt;
+use tracing::{info, warn, error};
+
+use crate::mariadb::DbAddress;
+use crate::service::ServiceState;
+
+const FILE_PREFIX: &str = "booklore-";
+const FILE_SUFFIX: &str = ".sql.gz";
+/// Milliseconds keep two backups taken within the same second apart
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S%.3f";
+/// Also reads the whole-second names of backups made by older versions
+const TIMESTAMP_PARSE_FORMAT: &str = "%Y%m%d-%H%M%S%.f";
+
+/// How often the scheduler checks whether a backup is due
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Wait before the scheduler tries again after a failed backup
+const RETRY_AFTER_FAILURE: Duration = Duration::from_secs(3600);
+
+/// Backups cover the embedded server only; an external one is backed up by its owner
+const EXTERNAL_DATABASE_ERROR: &str = "Backup and restore are only available for the embedded database";
+
+/// A dump in the backups directory
+pub struct BackupFile {
+    pub path: PathBuf,
+    pub created: NaiveDateTime,
+}
+
+/// All backups in `dir`, newest first
+pub fn list_backups(dir: &Path) -> Vec<BackupFile> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<BackupFile> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let timestamp = name.strip_prefix(FILE_PREFIX)?.strip_suffix(FILE_SUFFIX)?;
+            let created = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_PARSE_FORMAT).ok()?;
+            Some(BackupFile { path: entry.path(), created })
+        })
+        .collect();
+
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created));
+    backups
+}
+
+/// Dump the booklore schema of the running server into a new backup file
+pub async fn create_backup(address: &DbAddress, dir: &Path) -> Result<PathBuf, String> {
+    let credentials = crate::credentials::load()?
+        .filter(|c| c.provisioned)
+        .ok_or("Database credentials have not been set up yet")?;
+
+    let (address, dir) = (address.clone(), dir.to_path_buf());
+    tokio::task::spawn_blocking(move || dump(&address, &credentials.root_password, &dir))
+        .await
+        .map_err(|e| format!("Backup task failed: {}", e))?
+}
+
+/// Run mariadb-dump and gzip its output into `dir`
+fn dump(address: &DbAddress, root_password: &str, dir: &Path) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    remove_partial_files(dir);
+
+    let name = format!("{}{}{}", FILE_PREFIX, Local::now().format(TIMESTAMP_FORMAT), FILE_SUFFIX);
+    let path = dir.join(&name);
+    let partial_path = dir.join(format!("{}.partial", name));
+
+    info!("Backing up database to {:?}", path);
+
+    let mut child = Command::new(crate::mariadb::get_tool_path("mariadb-dump"))
+        .arg("--no-defaults")
+        .args(address.client_args())
+        .arg("-u")
+        .arg("root")
+        .arg("--single-transaction")  // Consistent snapshot while the backend keeps writing
+        .arg("--quick")
+        .arg("--routines")
+        .arg("--triggers")
+        .arg("--hex-blob")
+        .arg("booklore")
+        .env("MYSQL_PWD", root_password)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run mariadb-dump: {}", e))?;
+
+    // Drain stderr separately so a chatty dump can never block on a full pipe
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let stderr_reader = std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let written = write_compressed(&mut stdout, &partial_path);
+
+    let status = child.wait();
+    let stderr_text = stderr_reader.join().unwrap_or_default();
+
+    let result = match (written, status) {
+        (Err(e), _) => Err(format!("Failed to write backup: {}", e)),
+        (_, Err(e)) => Err(format!("mariadb-dump failed: {}", e)),
+        (_, Ok(status)) if !status.success() => Err(format!("mariadb-dump failed ({}): {}", status, stderr_text.trim())),
+        _ => std::fs::rename(&partial_path, &path)
+            .map_err(|e| format!("Failed to finish backup: {}", e)),
+    };
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial_path);
+        return Err(e);
+    }
+
+    info!("Backup written to {:?}", path);
+    Ok(path)
+}
+
+/// Gzip `source` into a new file readable by the current user only; it holds the whole library
+fn write_compressed(source: &mut impl Read, path: &Path) -> std::io::Result<()> {
+    let file = crate::config::create_private_file(path)?;
+    let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+    std::io::copy(source, &mut encoder)?;
+    let mut writer = encoder.finish()?;
+    writer.flush()?;
+    writer.get_ref().sync_all()
+}
+
+/// Remove dumps left behind by a crash in the middle of a backup
+fn remove_partial_files(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with(FILE_PREFIX) && name.ends_with(".partial") {
+            warn!("Removing incomplete backup {:?}", entry.path());
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Delete backups outside the retention policy: the newest backup of each of
+/// the last `keep_daily` days and of each of the last `keep_weekly` weeks is kept
+pub fn prune(dir: &Path, keep_daily: u32, keep_weekly: u32) {
+    let mut days = Vec::new();
+    let mut weeks = Vec::new();
+
+    for backup in list_backups(dir) {
+        let day = backup.created.date();
+        let week = backup.created.iso_week();
+        let mut keep = false;
+
+        if !days.contains(&day) && days.len() < keep_daily as usize {
+            days.push(day);
+            keep = true;
+        }
+        if !weeks.contains(&week) && weeks.len() < keep_weekly as usize {
+            weeks.push(week);
+            keep = true;
+        }
+
+        if !keep {
+            info!("Removing old backup {:?}", backup.path);
+            if let Err(e) = std::fs::remove_file(&backup.path) {
+                warn!("Failed to remove old backup {:?}: {}", backup.path, e);
+            }
+        }
+    }
+}
+
+/// Back up the running database, apply retention and notify the user of the result
+pub async fn run_backup(app: &AppHandle) -> Result<PathBuf, String> {
+    let state = app.state::<crate::AppState>();
+    if state.backup_running.swap(true, Ordering::SeqCst) {
+        return Err("A backup is already running".to_string());
+    }
+
+    let result = backup_and_prune(app).await;
+    state.backup_running.store(false, Ordering::SeqCst);
+
+    match &result {
+        Ok(path) => {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            notify(app, "Backup complete", &format!("Library database saved as {}", name));
+        }
+        Err(e) => {
+            error!("Backup failed: {}", e);
+            notify(app, "Backup failed", e);
+        }
+    }
+    result
+}
+
+async fn backup_and_prune(app: &AppHandle) -> Result<PathBuf, String> {
+    let state = app.state::<crate::AppState>();
+    let config = state.settings().await?;
+    if config.external_database.enabled {
+        return Err(EXTERNAL_DATABASE_ERROR.to_string());
+    }
+    if state.supervisor.state("mariadb").await != ServiceState::Healthy {
+        return Err("The database is not running".to_string());
+    }
+
+    let path = create_backup(&DbAddress::from_config(&config), &config.paths.backups_dir).await?;
+    prune(&config.paths.backups_dir, config.backup.keep_daily, config.backup.keep_weekly);
+    Ok(path)
+}
+
+fn notify(app: &AppHandle, title: &str, body: &str) {
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        warn!("Failed to show notification: {}", e);
+    }
+}
+
+/// Run scheduled backups in the background for the lifetime of the app
+pub fn spawn_scheduler(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut last_failure: Option<Instant> = None;
+
+        loop {
+            tokio::time::sleep(SCHEDULE_CHECK_INTERVAL).await;
+
+            let state = app.state::<crate::AppState>();
+            if state.is_shutting_down.load(Ordering::SeqCst) {
+                return;
+            }
+
+            // Settings are re-read every time so changes apply without a restart
+            let Ok(config) = state.settings().await else {
+                continue;
+            };
+            if !config.backup.enabled
+                || config.external_database.enabled
+                || state.supervisor.state("mariadb").await != ServiceState::Healthy
+                || last_failure.is_some_and(|t| t.elapsed() < RETRY_AFTER_FAILURE)
+            {
+                continue;
+            }
+
+            let interval = chrono::Duration::hours(config.backup.interval_hours as i64);
+            let due = match list_backups(&config.paths.backups_dir).first() {
+                Some(latest) => Local::now().naive_local() - latest.created >= interval,
+                None => true,
+            };
+            if !due {
+                continue;
+            }
+
+            info!("Scheduled backup is due");
+            last_failure = run_backup(&app).await.err().map(|_| Instant::now());
+        }
+    });
+}
+
+/// Backup file given as `--restore <file>` on the command line
+pub fn restore_arg() -> Option<PathBuf> {
+    parse_restore_arg(std::env::args().skip(1))
+}
+
+fn parse_restore_arg(mut args: impl Iterator<Item = String>) -> Option<PathBuf> {
+    while let Some(arg) = args.next() {
+        if arg == "--restore" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--restore=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+/// Resolve a backup picked in the UI. Only the name of a file listed in the
+/// backups directory is accepted, never a path to anywhere else.
+pub fn resolve_backup(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let not_found = || format!("{:?} is not a backup in {:?}", name, dir);
+    if Path::new(name).file_name() != Some(std::ffi::OsStr::new(name)) {
+        return Err(not_found());
+    }
+
+    let dir = dir.canonicalize().map_err(|_| not_found())?;
+    let path = dir.join(name).canonicalize().map_err(|_| not_found())?;
+    // A symlink resolves to somewhere else and is not listed under its own name
+    let listed = list_backups(&dir).iter().any(|backup| backup.path == path);
+    if path.parent() != Some(dir.as_path()) || !listed {
+        return Err(not_found());
+    }
+    Ok(path)
+}
+
+/// Restore requested on the command line: runs instead of the normal startup
+pub fn begin_restore(app: &AppHandle, dump: PathBuf) {
+    let state = app.state::<crate::AppState>();
+    if state.backup_running.swap(true, Ordering::SeqCst) {
+        let _ = report_restore(app, &dump, Err("A backup or restore is already running".to_string()));
+        return;
+    }
+    // Set right away so the start request from the UI leaves the services to us
+    state.restoring.store(true, Ordering::SeqCst);
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if run_restore(&app, &dump).await.is_err() {
+            // Nothing may have been started yet if the restore gave up early
+            let state = app.state::<crate::AppState>();
+            if !state.supervisor.is_running("backend").await {
+                let _ = crate::launch_services(app.clone(), state).await;
+            }
+        }
+    });
+}
+
+/// Replace the library database with the contents of a backup file.
+/// The data directory is snapshotted first and put back if the backend
+/// does not come up healthy on the restored data.
+pub async fn restore_backup(app: &AppHandle, dump: &Path) -> Result<(), String> {
+    let state = app.state::<crate::AppState>();
+    if state.backup_running.swap(true, Ordering::SeqCst) {
+        return report_restore(app, dump, Err("A backup or restore is already running".to_string()));
+    }
+    state.restoring.store(true, Ordering::SeqCst);
+    run_restore(app, dump).await
+}
+
+/// Run a restore that already holds `backup_running` and `restoring`, then release both
+async fn run_restore(app: &AppHandle, dump: &Path) -> Result<(), String> {
+    let state = app.state::<crate::AppState>();
+    let result = if dump.is_file() {
+        restore(app, dump).await
+    } else {
+        Err(format!("Backup file {:?} not found", dump))
+    };
+
+    state.restoring.store(false, Ordering::SeqCst);
+    state.backup_running.store(false, Ordering::SeqCst);
+    report_restore(app, dump, result)
+}
+
+fn report_restore(app: &AppHandle, dump: &Path, result: Result<(), String>) -> Result<(), String> {
+    match &result {
+        Ok(()) => {
+            let name = dump.file_name().unwrap_or_default().to_string_lossy();
+            notify(app, "Restore complete", &format!("Library database restored from {}", name));
+        }
+        Err(e) => {
+            error!("Restore failed: {}", e);
+            notify(app, "Restore failed", e);
+        }
+    }
+    result
+}
+
+async fn restore(app: &AppHandle, dump: &Path) -> Result<(), String> {
+    let state = app.state::<crate::AppState>();
+    // The database is started here directly, so it needs the same transport as a normal launch
+    let config = crate::backend::with_transport_fallback(app, state.settings().await?).await;
+    if config.external_database.enabled {
+        return Err(EXTERNAL_DATABASE_ERROR.to_string());
+    }
+    let data_dir = config.paths.data_dir.clone();
+    let snapshot = snapshot_path(&data_dir);
+
+    // No other launch may bring the services up while the data directory is swapped
+    let launching = state.launching.clone().lock_owned().await;
+
+    info!("Restoring database from {:?}", dump);
+    crate::tray::update_status(app, "Restoring database");
+    crate::emit_status(app, "mariadb", "active", "Restoring database from backup...", 15);
+
+    if let Err(e) = crate::shutdown::stop_services(app).await {
+        // Whatever did stop has to come back rather than leaving the library down
+        let _ = crate::launch_locked(app.clone(), state.clone(), &launching).await;
+        return Err(format!("Failed to stop the services before restoring: {}", e));
+    }
+
+    // Copy with the server stopped so the snapshot is consistent
+    let (from, to) = (data_dir.clone(), snapshot.clone());
+    let snapshotted = tokio::task::spawn_blocking(move || {
+        if to.exists() {
+            std::fs::remove_dir_all(&to)
+                .map_err(|e| format!("Failed to remove old snapshot: {}", e))?;
+        }
+        crate::mariadb::copy_dir_recursive(&from, &to)
+    })
+    .await
+    .map_err(|e| format!("Snapshot task failed: {}", e))
+    .and_then(|result| result);
+
+    if let Err(e) = snapshotted {
+        let _ = crate::launch_locked(app.clone(), state.clone(), &launching).await;
+        return Err(format!("Failed to snapshot the database before restoring: {}", e));
+    }
+    info!("Snapshot of {:?} saved to {:?}", data_dir, snapshot);
+
+    match load_and_start(app, &state, &config, dump, &launching).await {
+        Ok(()) => {
+            let _ = tokio::task::spawn_blocking(move || std::fs::remove_dir_all(&snapshot)).await;
+            info!("Database restored from {:?}", dump);
+            Ok(())
+        }
+        Err(e) => {
+            error!("Restore failed, rolling back: {}", e);
+            crate::emit_status(app, "mariadb", "active", "Restore failed, putting the previous database back...", 15);
+            match rollback(app, &data_dir, &snapshot, &launching).await {
+                Ok(()) => Err(format!("{}. The previous database was put back.", e)),
+                Err(rollback_error) => Err(format!(
+                    "{}. Rolling back also failed: {}. The previous data directory is kept at {:?}",
+                    e, rollback_error, snapshot
+                )),
+            }
+        }
+    }
+}
+
+/// Load the dump into an empty schema, then bring everything up and check the backend
+async fn load_and_start(
+    app: &AppHandle,
+    state: &crate::AppState,
+    config: &crate::config::RunnerConfig,
+    dump: &Path,
+    launching: &OwnedMutexGuard<()>,
+) -> Result<(), String> {
+    // Quit may have been chosen while the snapshot was copied
+    if state.is_shutting_down.load(Ordering::SeqCst) {
+        return Err("BookLore is shutting down".to_string());
+    }
+    let mariadb = Arc::new(crate::mariadb::MariaDbService::new(config, state.mariadb_logs.clone()));
+    state.supervisor.start(app, mariadb).await?;
+
+    let credentials = crate::credentials::load()?
+        .filter(|c| c.provisioned)
+        .ok_or("Database credentials have not been set up yet")?;
+    let address = DbAddress::from_config(config);
+    crate::mariadb::recreate_database(&address, &credentials).await?;
+
+    let (password, dump) = (credentials.root_password.clone(), dump.to_path_buf());
+    tokio::task::spawn_blocking(move || load_dump(&address, &password, &dump))
+        .await
+        .map_err(|e| format!("Restore task failed: {}", e))??;
+    // Whatever the crash check found is gone with the old schema
+    state.damaged_tables.clear();
+
+    crate::launch_locked(app.clone(), app.state::<crate::AppState>(), launching).await?;
+
+    if !crate::backend::is_healthy(config.ports.backend).await {
+        return Err("The backend is not healthy after the restore".to_string());
+    }
+    Ok(())
+}
+
+/// Feed a (gzipped) SQL dump into the booklore schema through the mariadb client
+fn load_dump(address: &DbAddress, root_password: &str, dump: &Path) -> Result<(), String> {
+    let file = File::open(dump)
+        .map_err(|e| format!("Failed to open backup {:?}: {}", dump, e))?;
+    let mut reader: Box<dyn Read> = if dump.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(GzDecoder::new(BufReader::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+
+    let mut child = Command::new(crate::mariadb::get_tool_path("mariadb"))
+        .arg("--no-defaults")
+        .args(address.client_args())
+        .arg("-u")
+        .arg("root")
+        .arg("booklore")
+        .env("MYSQL_PWD", root_password)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run mariadb client: {}", e))?;
+
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let stderr_reader = std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let copied = std::io::copy(&mut reader, &mut stdin);
+    drop(stdin);
+
+    let status = child.wait()
+        .map_err(|e| format!("mariadb client failed: {}", e))?;
+    let stderr_text = stderr_reader.join().unwrap_or_default();
+
+    // A client error closes the pipe, so check its exit status before the copy result
+    if !status.success() {
+        return Err(format!("Loading the backup failed: {}", stderr_text.trim()));
+    }
+    copied.map_err(|e| format!("Failed to read backup {:?}: {}", dump, e))?;
+    Ok(())
+}
+
+/// Put the snapshot back in place of the restored data directory and start again
+async fn rollback(
+    app: &AppHandle,
+    data_dir: &Path,
+    snapshot: &Path,
+    launching: &OwnedMutexGuard<()>,
+) -> Result<(), String> {
+    crate::shutdown::stop_services(app).await?;
+
+    let (data_dir, snapshot) = (data_dir.to_path_buf(), snapshot.to_path_buf());
+    tokio::task::spawn_blocking(move || swap_in_snapshot(&data_dir, &snapshot))
+        .await
+        .map_err(|e| format!("Rollback task failed: {}", e))??;
+
+    crate::launch_locked(app.clone(), app.state::<crate::AppState>(), launching).await
+}
+
+/// Move the restored data aside, move the snapshot into its place, then delete the restored data.
+/// If the snapshot cannot be moved, the restored data is left at `<data dir>.failed-restore`.
+fn swap_in_snapshot(data_dir: &Path, snapshot: &Path) -> Result<(), String> {
+    let failed = data_dir.with_file_name(format!("{}.failed-restore", dir_name(data_dir)));
+    let _ = std::fs::remove_dir_all(&failed);
+    if data_dir.exists() {
+        std::fs::rename(data_dir, &failed)
+            .map_err(|e| format!("Failed to move restored data aside: {}", e))?;
+    }
+    std::fs::rename(snapshot, data_dir)
+        .map_err(|e| format!("Failed to put the snapshot back: {}", e))?;
+    let _ = std::fs::remove_dir_all(&failed);
+    Ok(())
+}
+
+/// Where the data directory is copied before a restore, next to the original
+fn snapshot_path(data_dir: &Path) -> PathBuf {
+    data_dir.with_file_name(format!("{}.before-restore", dir_name(data_dir)))
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A backups directory holding empty dumps taken at the given times (`YYYYMMDD-HHMMSS[.mmm]`)
+    fn backups(test: &str, timestamps: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("booklore-backup-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for timestamp in timestamps {
+            std::fs::write(dir.join(format!("{}{}{}", FILE_PREFIX, timestamp, FILE_SUFFIX)), "").unwrap();
+        }
+        dir
+    }
+
+    /// Timestamps of the backups left in `dir`, newest first
+    fn remaining(dir: &Path) -> Vec<String> {
+        list_backups(dir)
+            .iter()
+            .map(|backup| backup.created.format(TIMESTAMP_PARSE_FORMAT).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn lists_backups_newest_first_and_ignores_other_files() {
+        let dir = backups("list", &["20240301-120000", "20240302-080000", "20240301-180000"]);
+        std::fs::write(dir.join("booklore-20240303-000000.sql.gz.partial"), "").unwrap();
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+        assert_eq!(remaining(&dir), ["20240302-080000", "20240301-180000", "20240301-120000"]);
+    }
+
+    #[test]
+    fn orders_backups_within_the_same_second() {
+        // Whole-second names come from older versions
+        let dir = backups("subsecond", &["20240301-120000.250", "20240301-120000", "20240301-120000.900"]);
+        assert_eq!(remaining(&dir), ["20240301-120000.900", "20240301-120000.250", "20240301-120000"]);
+
+        let name = format!("{}", Local::now().format(TIMESTAMP_FORMAT));
+        assert!(NaiveDateTime::parse_from_str(&name, TIMESTAMP_PARSE_FORMAT).is_ok(), "{}", name);
+    }
+
+    #[test]
+    fn keeps_the_newest_backup_of_each_day() {
+        let dir = backups("daily", &[
+            "20240301-020000", "20240301-140000", "20240302-020000",
+            "20240302-140000", "20240303-020000",
+        ]);
+        prune(&dir, 2, 0);
+        assert_eq!(remaining(&dir), ["20240303-020000", "20240302-140000"]);
+    }
+
+    #[test]
+    fn keeps_the_newest_backup_of_each_week() {
+        // Monday 4, Sunday 10 and Monday 11 March 2024: weeks 10 and 11
+        let dir = backups("weekly", &["20240304-020000", "20240310-020000", "20240311-020000"]);
+        prune(&dir, 0, 4);
+        assert_eq!(remaining(&dir), ["20240311-020000", "20240310-020000"]);
+    }
+
+    #[test]
+    fn daily_and_weekly_retention_add_up() {
+        let dir = backups("combined", &[
+            "20240212-020000", "20240219-020000", "20240226-020000",
+            "20240304-020000", "20240305-020000", "20240306-020000",
+        ]);
+        prune(&dir, 2, 3);
+        assert_eq!(remaining(&dir), ["20240306-020000", "20240305-020000", "20240226-020000", "20240219-020000"]);
+    }
+
+    #[test]
+    fn weeks_run_across_the_year_boundary() {
+        // 29 Dec 2020 and 2 Jan 2021 are both in ISO week 53 of 2020; 5 Jan 2021 is week 1 of 2021
+        let dir = backups("week53", &["20201229-020000", "20210102-020000", "20210105-020000"]);
+        prune(&dir, 1, 2);
+        assert_eq!(remaining(&dir), ["20210105-020000", "20210102-020000"]);
+
+        // 30 Dec 2019 already belongs to week 1 of 2020
+        let dir = backups("week1", &["20191230-020000", "20200102-020000"]);
+        prune(&dir, 0, 1);
+        assert_eq!(remaining(&dir), ["20200102-020000"]);
+    }
+
+    #[test]
+    fn compressed_dump_is_private_and_complete() {
+        let dir = backups("write", &[]);
+        let path = dir.join("booklore-20240301-120000.000.sql.gz.partial");
+        write_compressed(&mut "CREATE TABLE book (id INT);".as_bytes(), &path).unwrap();
+
+        let mut sql = String::new();
+        GzDecoder::new(File::open(&path).unwrap()).read_to_string(&mut sql).unwrap();
+        assert_eq!(sql, "CREATE TABLE book (id INT);");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn reads_the_restore_argument_in_both_forms() {
+        assert_eq!(parse_restore_arg(args(&["--restore", "/tmp/a.sql.gz"])), Some(PathBuf::from("/tmp/a.sql.gz")));
+        assert_eq!(parse_restore_arg(args(&["-v", "--restore=/tmp/b.sql"])), Some(PathBuf::from("/tmp/b.sql")));
+        assert_eq!(parse_restore_arg(args(&["--restore"])), None);
+        assert_eq!(parse_restore_arg(args(&["--minimized"])), None);
+    }
+
+    #[test]
+    fn snapshot_sits_next_to_the_data_directory() {
+        assert_eq!(snapshot_path(Path::new("/lib/BookLore/data")), PathBuf::from("/lib/BookLore/data.before-restore"));
+    }
+
+    #[test]
+    fn resolves_only_listed_backup_names() {
+        let dir = backups("resolve", &["20240301-120000"]);
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+        let name = "booklore-20240301-120000.sql.gz";
+        assert_eq!(resolve_backup(&dir, name).unwrap(), dir.canonicalize().unwrap().join(name));
+
+        for rejected in ["notes.txt", "booklore-20240302-120000.sql.gz", "", "..", "../etc/passwd"] {
+            assert!(resolve_backup(&dir, rejected).is_err(), "{:?}", rejected);
+        }
+        let outside = dir.parent().unwrap().join(name);
+        assert!(resolve_backup(&dir, &outside.to_string_lossy()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_a_backup_symlinked_from_elsewhere() {
+        let dir = backups("symlink", &[]);
+        let elsewhere = backups("symlink-target", &["20240301-120000"]);
+        let name = "booklore-20240301-120000.sql.gz";
+        std::os::unix::fs::symlink(elsewhere.join(name), dir.join(name)).unwrap();
+        assert!(resolve_backup(&dir, name).is_err());
+    }
+
+    /// A data directory and its snapshot, each holding one marker file
+    fn data_and_snapshot(test: &str) -> (PathBuf, PathBuf) {
+        let root = backups(test, &[]);
+        let (data_dir, snapshot) = (root.join("data"), snapshot_path(&root.join("data")));
+        std::fs::create_dir_all(&data_dir).unwrap();
+        std::fs::create_dir_all(&snapshot).unwrap();
+        std::fs::write(data_dir.join("marker"), "restored").unwrap();
+        std::fs::write(snapshot.join("marker"), "original").unwrap();
+        (data_dir, snapshot)
+    }
+
+    #[test]
+    fn rollback_puts_the_snapshot_in_place() {
+        let (data_dir, snapshot) = data_and_snapshot("rollback");
+        swap_in_snapshot(&data_dir, &snapshot).unwrap();
+        assert_eq!(std::fs::read_to_string(data_dir.join("marker")).unwrap(), "original");
+        assert!(!snapshot.exists());
+        assert!(!data_dir.with_file_name("data.failed-restore").exists());
+    }
+
+    #[test]
+    fn rollback_keeps_the_restored_data_when_the_snapshot_is_missing() {
+        let (data_dir, snapshot) = data_and_snapshot("rollback-missing");
+        std::fs::remove_dir_all(&snapshot).unwrap();
+        assert!(swap_in_snapshot(&data_dir, &snapshot).is_err());
+        let failed = data_dir.with_file_name("data.failed-restore");
+        assert_eq!(std::fs::read_to_string(failed.join("marker")).unwrap(), "restored");
+    }
+}
@@ -0,0 +1,584 @@
+// Runner Configuration Module
+// Loads, validates and saves config/runner.toml
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+/// Current version of the config file layout
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Sent to the Settings window instead of the external database password
+pub const PASSWORD_PLACEHOLDER: &str = "\u{2022}\u{2022}\u{2022}\u{2022}\u{2022}\u{2022}\u{2022}\u{2022}";
+
+/// Settings for the runner, persisted in config/runner.toml
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunnerConfig {
+    pub version: u32,
+    pub ports: PortsConfig,
+    pub backend: BackendConfig,
+    pub mariadb: MariaDbConfig,
+    pub external_database: ExternalDatabaseConfig,
+    pub paths: PathsConfig,
+    pub backup: BackupConfig,
+    pub downloads: DownloadsConfig,
+}
+
+/// Local TCP ports
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortsConfig {
+    pub frontend: u16,
+    pub backend: u16,
+    pub mariadb: u16,
+}
+
+/// JVM settings for the Spring Boot backend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackendConfig {
+    pub heap_max_mb: u32,
+    pub heap_min_mb: u32,
+    /// Seconds the JVM gets to exit after SIGTERM before it is killed
+    pub stop_timeout_secs: u64,
+}
+
+/// Embedded database server settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MariaDbConfig {
+    /// Version downloaded when no MariaDB is bundled or installed
+    pub version: String,
+    /// Reach the server through its Unix socket only, without opening a TCP port.
+    /// The backend's Connector/J needs JNA on its classpath for this, which is checked before it starts.
+    pub socket_only: bool,
+    /// Seconds the server gets to flush and exit after the shutdown request before it is killed
+    pub stop_timeout_secs: u64,
+}
+
+/// An existing MariaDB/MySQL server used instead of the embedded one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExternalDatabaseConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    /// Database (schema) the backend stores its tables in
+    pub schema: String,
+}
+
+/// Where library data lives
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PathsConfig {
+    /// MariaDB data directory
+    pub data_dir: PathBuf,
+    pub books_dir: PathBuf,
+    pub bookdrop_dir: PathBuf,
+    /// Where database dumps are written
+    pub backups_dir: PathBuf,
+}
+
+/// Scheduled database backups
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    pub enabled: bool,
+    /// Hours between scheduled backups
+    pub interval_hours: u32,
+    /// Number of days for which the newest backup is kept
+    pub keep_daily: u32,
+    /// Number of weeks for which the newest backup is kept
+    pub keep_weekly: u32,
+}
+
+/// Where the JRE and MariaDB come from when neither is bundled nor installed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadsConfig {
+    /// Base URL of the Adoptium API, or of a mirror serving the same API
+    pub adoptium_url: String,
+    /// Base URL the JRE archive is downloaded from instead of the host in the API's
+    /// package link (GitHub); the link's path is appended. Empty uses the link as is.
+    pub adoptium_binary_url: String,
+    /// Base URL of archive.mariadb.org, or of a mirror with the same layout
+    pub mariadb_url: String,
+    /// Folder searched for pre-downloaded runtime archives before anything is downloaded.
+    /// Empty means `offline` next to the database directory; see `RunnerConfig::download_sources`.
+    pub offline_dir: PathBuf,
+}
+
+impl Default for RunnerConfig {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            ports: PortsConfig::default(),
+            backend: BackendConfig::default(),
+            mariadb: MariaDbConfig::default(),
+            external_database: ExternalDatabaseConfig::default(),
+            paths: PathsConfig::default(),
+            backup: BackupConfig::default(),
+            downloads: DownloadsConfig::default(),
+        }
+    }
+}
+
+impl Default for PortsConfig {
+    fn default() -> Self {
+        Self {
+            frontend: crate::constants::FRONTEND_PORT,
+            backend: crate::constants::BACKEND_PORT,
+            mariadb: crate::constants::MARIADB_PORT,
+        }
+    }
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        Self {
+            heap_max_mb: 512,
+            heap_min_mb: 128,
+            stop_timeout_secs: 30,
+        }
+    }
+}
+
+impl Default for MariaDbConfig {
+    fn default() -> Self {
+        Self {
+            version: crate::constants::MARIADB_VERSION.to_string(),
+            socket_only: false,
+            stop_timeout_secs: 120,
+        }
+    }
+}
+
+impl Default for ExternalDatabaseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".to_string(),
+            port: 3306,
+            user: "booklore".to_string(),
+            password: String::new(),
+            schema: "booklore".to_string(),
+        }
+    }
+}
+
+impl Default for PathsConfig {
+    fn default() -> Self {
+        let app_data_dir = crate::get_app_data_dir();
+        Self {
+            data_dir: app_data_dir.join("data"),
+            books_dir: app_data_dir.join("books"),
+            bookdrop_dir: app_data_dir.join("bookdrop"),
+            backups_dir: app_data_dir.join("backups"),
+        }
+    }
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: 24,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
+}
+
+impl Default for DownloadsConfig {
+    fn default() -> Self {
+        Self {
+            adoptium_url: crate::constants::ADOPTIUM_API.to_string(),
+            adoptium_binary_url: String::new(),
+            mariadb_url: crate::constants::MARIADB_ARCHIVE.to_string(),
+            offline_dir: PathBuf::new(),
+        }
+    }
+}
+
+impl RunnerConfig {
+    /// Copy for the webview, with the stored password replaced by a placeholder
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        if !config.external_database.password.is_empty() {
+            config.external_database.password = PASSWORD_PLACEHOLDER.to_string();
+        }
+        config
+    }
+
+    /// Put the stored password back where the Settings window left the placeholder untouched
+    pub fn keep_password(&mut self, stored: &RunnerConfig) {
+        if self.external_database.password == PASSWORD_PLACEHOLDER {
+            self.external_database.password = stored.external_database.password.clone();
+        }
+    }
+
+    /// Download settings with the offline folder resolved; by default it sits next to
+    /// the database directory, so moving the data to another disk moves it too
+    pub fn download_sources(&self) -> DownloadsConfig {
+        let mut sources = self.downloads.clone();
+        if sources.offline_dir.as_os_str().is_empty() {
+            let data_dir = &self.paths.data_dir;
+            sources.offline_dir = data_dir.parent().unwrap_or(data_dir).join("offline");
+        }
+        sources
+    }
+
+    /// Check the settings, collecting every problem into one message
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+
+        let ports = [
+            ("Frontend", self.ports.frontend),
+            ("Backend", self.ports.backend),
+            ("MariaDB", self.ports.mariadb),
+        ];
+        for (name, port) in ports {
+            if port < 1024 {
+                problems.push(format!("{} port must be between 1024 and 65535", name));
+            }
+        }
+        for (i, (name, port)) in ports.iter().enumerate() {
+            if let Some((other, _)) = ports[i + 1..].iter().find(|(_, p)| p == port) {
+                problems.push(format!("{} and {} ports must differ", name, other));
+            }
+        }
+
+        if self.backend.heap_max_mb < 256 {
+            problems.push("Maximum heap must be at least 256 MB".to_string());
+        }
+        if self.backend.heap_min_mb == 0 || self.backend.heap_min_mb > self.backend.heap_max_mb {
+            problems.push("Initial heap must be between 1 MB and the maximum heap".to_string());
+        }
+
+        let timeouts = [
+            ("Backend", self.backend.stop_timeout_secs),
+            ("Database", self.mariadb.stop_timeout_secs),
+        ];
+        for (name, secs) in timeouts {
+            if secs == 0 || secs > 600 {
+                problems.push(format!("{} stop timeout must be between 1 and 600 seconds", name));
+            }
+        }
+
+        let version_ok = {
+            let parts: Vec<&str> = self.mariadb.version.split('.').collect();
+            parts.len() == 3 && parts.iter().all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+        };
+        if !version_ok {
+            problems.push(format!("MariaDB version '{}' must look like 11.4.5", self.mariadb.version));
+        }
+
+        let external = &self.external_database;
+        if external.enabled {
+            if external.host.trim().is_empty() {
+                problems.push("External database host must not be empty".to_string());
+            }
+            if external.port == 0 {
+                problems.push("External database port must be between 1 and 65535".to_string());
+            }
+            if external.user.trim().is_empty() {
+                problems.push("External database user must not be empty".to_string());
+            }
+            let schema_ok = !external.schema.is_empty()
+                && external.schema.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+            if !schema_ok {
+                problems.push("External database name may only contain letters, digits, _ and $".to_string());
+            }
+        }
+
+        let paths = [
+            ("Database directory", &self.paths.data_dir),
+            ("Books directory", &self.paths.books_dir),
+            ("BookDrop directory", &self.paths.bookdrop_dir),
+            ("Backup directory", &self.paths.backups_dir),
+        ];
+        for (name, path) in paths {
+            if !path.is_absolute() {
+                problems.push(format!("{} must be an absolute path", name));
+            }
+        }
+        let offline_dir = &self.downloads.offline_dir;
+        if !offline_dir.as_os_str().is_empty() && !offline_dir.is_absolute() {
+            problems.push("Offline folder must be empty or an absolute path".to_string());
+        }
+
+        // Name, URL and whether it may be left empty
+        let urls = [
+            ("Adoptium", &self.downloads.adoptium_url, false),
+            ("Java runtime mirror", &self.downloads.adoptium_binary_url, true),
+            ("MariaDB", &self.downloads.mariadb_url, false),
+        ];
+        for (name, url, optional) in urls {
+            if optional && url.is_empty() {
+                continue;
+            }
+            let url_ok = url.parse::<reqwest::Url>()
+                .is_ok_and(|u| matches!(u.scheme(), "http" | "https") && u.has_host());
+            if !url_ok {
+                problems.push(format!("{} download URL must be an http:// or https:// address", name));
+            }
+        }
+
+        if self.backup.interval_hours == 0 || self.backup.interval_hours > 24 * 30 {
+            problems.push("Backup interval must be between 1 and 720 hours".to_string());
+        }
+        if self.backup.keep_daily == 0 && self.backup.keep_weekly == 0 {
+            problems.push("At least one daily or weekly backup must be kept".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; "))
+        }
+    }
+}
+
+/// Path of config/runner.toml
+pub fn get_config_path() -> PathBuf {
+    crate::get_app_data_dir().join("config").join("runner.toml")
+}
+
+/// Load the config file, writing defaults on first run.
+/// A broken, invalid or too new file is an error: falling back to defaults would
+/// point the database and library at the default folders.
+pub fn load() -> Result<RunnerConfig, String> {
+    load_from(&get_config_path())
+}
+
+fn load_from(path: &Path) -> Result<RunnerConfig, String> {
+    if !path.exists() {
+        let config = RunnerConfig::default();
+        if let Err(e) = save_to(&config, path) {
+            warn!("Failed to write default config: {}", e);
+        }
+        info!("Created default config at {:?}", path);
+        return Ok(config);
+    }
+
+    let mut config = read_from(path)?;
+
+    if config.version > CONFIG_VERSION {
+        return Err(format!(
+            "Config {:?} has version {}, but this BookLore only supports up to version {}. Update BookLore or remove the file.",
+            path, config.version, CONFIG_VERSION
+        ));
+    }
+
+    config.validate()
+        .map_err(|e| format!("Invalid settings in {:?}: {}", path, e))?;
+
+    if config.version < CONFIG_VERSION {
+        info!("Upgrading config from version {} to {}", config.version, CONFIG_VERSION);
+        config.version = CONFIG_VERSION;
+        if let Err(e) = save_to(&config, path) {
+            warn!("Failed to save upgraded config: {}", e);
+        }
+    }
+
+    info!("Loaded config from {:?}", path);
+    Ok(config)
+}
+
+/// Validate and persist the config
+pub fn save(config: &RunnerConfig) -> Result<(), String> {
+    config.validate()?;
+    save_to(config, &get_config_path())
+}
+
+fn read_from(path: &Path) -> Result<RunnerConfig, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read config {:?}: {}", path, e))?;
+    toml::from_str(&content)
+        .map_err(|e| format!("Failed to parse config {:?}: {}", path, e))
+}
+
+fn save_to(config: &RunnerConfig, path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let content = toml::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+    // It may hold the external database password
+    write_private_file(path, &content)
+        .map_err(|e| format!("Failed to write config: {}", e))
+}
+
+/// Create a fresh file readable by the current user only, replacing a stale one.
+/// The mode is set on creation, so the content is never exposed, not even briefly.
+pub fn create_private_file(path: &Path) -> std::io::Result<std::fs::File> {
+    let _ = std::fs::remove_file(path);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+/// Replace a file holding secrets. The content goes to a private temp file first,
+/// which is renamed over the old one, so a crash never leaves a truncated file behind.
+pub fn write_private_file(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    let mut file = create_private_file(&temp_path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&temp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config path in a fresh temporary directory
+    fn config_path(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("booklore-config-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("runner.toml")
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        RunnerConfig::default().validate().unwrap();
+    }
+
+    #[test]
+    fn validate_collects_every_problem() {
+        let mut config = RunnerConfig::default();
+        config.ports.backend = 80;
+        config.ports.mariadb = config.ports.frontend;
+        config.backend.heap_min_mb = 1024;
+        config.mariadb.version = "11.4".to_string();
+        config.paths.books_dir = PathBuf::from("books");
+        config.backup.keep_daily = 0;
+        config.backup.keep_weekly = 0;
+
+        let problems = config.validate().unwrap_err();
+        for expected in [
+            "Backend port must be between 1024 and 65535",
+            "Frontend and MariaDB ports must differ",
+            "Initial heap must be between 1 MB and the maximum heap",
+            "MariaDB version '11.4' must look like 11.4.5",
+            "Books directory must be an absolute path",
+            "At least one daily or weekly backup must be kept",
+        ] {
+            assert!(problems.contains(expected), "missing {:?} in {:?}", expected, problems);
+        }
+    }
+
+    #[test]
+    fn validate_checks_the_external_database_only_when_enabled() {
+        let mut config = RunnerConfig::default();
+        config.external_database.host = String::new();
+        config.external_database.schema = "book-lore".to_string();
+        config.validate().unwrap();
+
+        config.external_database.enabled = true;
+        let problems = config.validate().unwrap_err();
+        assert!(problems.contains("host must not be empty"), "{}", problems);
+        assert!(problems.contains("may only contain letters"), "{}", problems);
+    }
+
+    #[test]
+    fn validate_rejects_non_http_download_urls() {
+        let mut config = RunnerConfig::default();
+        config.downloads.mariadb_url = "ftp://mirror.example/mariadb".to_string();
+        assert!(config.validate().unwrap_err().contains("MariaDB download URL"));
+
+        config.downloads.mariadb_url = RunnerConfig::default().downloads.mariadb_url;
+        config.downloads.adoptium_binary_url = "mirror.example".to_string();
+        assert!(config.validate().unwrap_err().contains("Java runtime mirror download URL"));
+    }
+
+    #[test]
+    fn load_writes_defaults_on_first_run() {
+        let path = config_path("first-run");
+        assert_eq!(load_from(&path).unwrap(), RunnerConfig::default());
+        assert_eq!(read_from(&path).unwrap(), RunnerConfig::default());
+    }
+
+    #[test]
+    fn load_rejects_a_broken_file() {
+        let path = config_path("broken");
+        std::fs::write(&path, "[ports\nfrontend = ").unwrap();
+        assert!(load_from(&path).unwrap_err().starts_with("Failed to parse config"));
+        // The user's file is left alone
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[ports\nfrontend = ");
+    }
+
+    #[test]
+    fn load_rejects_a_newer_file() {
+        let path = config_path("newer");
+        std::fs::write(&path, format!("version = {}\n", CONFIG_VERSION + 1)).unwrap();
+        assert!(load_from(&path).unwrap_err().contains("only supports up to version"));
+    }
+
+    #[test]
+    fn load_rejects_invalid_settings() {
+        let path = config_path("invalid");
+        std::fs::write(&path, "[ports]\nfrontend = 80\n").unwrap();
+        let error = load_from(&path).unwrap_err();
+        assert!(error.starts_with("Invalid settings"), "{}", error);
+        assert!(error.contains("Frontend port"), "{}", error);
+    }
+
+    #[test]
+    fn load_upgrades_an_older_file() {
+        let path = config_path("older");
+        std::fs::write(&path, "version = 0\n[ports]\nfrontend = 7000\n").unwrap();
+        let config = load_from(&path).unwrap();
+        assert_eq!((config.version, config.ports.frontend), (CONFIG_VERSION, 7000));
+        assert_eq!(read_from(&path).unwrap(), config);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn saved_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = config_path("private");
+        // A leftover temp file from a crash must not make the save fail
+        std::fs::write(path.with_extension("toml.tmp"), "stale").unwrap();
+        save_to(&RunnerConfig::default(), &path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn keep_password_restores_only_the_placeholder() {
+        let mut stored = RunnerConfig::default();
+        stored.external_database.password = "secret".to_string();
+
+        let mut untouched = stored.redacted();
+        assert_eq!(untouched.external_database.password, PASSWORD_PLACEHOLDER);
+        untouched.keep_password(&stored);
+        assert_eq!(untouched.external_database.password, "secret");
+
+        let mut changed = stored.redacted();
+        changed.external_database.password = "new secret".to_string();
+        changed.keep_password(&stored);
+        assert_eq!(changed.external_database.password, "new secret");
+
+        let mut cleared = stored.redacted();
+        cleared.external_database.password = String::new();
+        cleared.keep_password(&stored);
+        assert_eq!(cleared.external_database.password, "");
+    }
+}
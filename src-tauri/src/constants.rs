@@ -9,5 +9,14 @@ pub const MARIADB_PORT: u16 = 13306;
 pub const MARIADB_VERSION: &str = "11.4.5";
 pub const JRE_VERSION: &str = "21";
 
+// Database
+pub const BACKEND_DB_POOL_SIZE: u32 = 10;
+
 // URLs
-pub const ADOPTIUM_API: &str = "https://api.adoptium.net/v3/binary/latest";
+pub const ADOPTIUM_API: &str = "https://api.adoptium.net/v3";
+pub const MARIADB_ARCHIVE: &str = "https://archive.mariadb.org";
+
+// Logs
+pub const LOG_MAX_BYTES: u64 = 10 * 1024 * 1024;
+pub const LOG_KEEP_FILES: usize = 5;
+pub const LOG_BUFFER_LINES: usize = 1000;
@@ -0,0 +1,145 @@
+// Database Credentials Module
+// Generates and stores the MariaDB passwords in config/db-credentials.toml (owner-only)
+
+use std::path::{Path, PathBuf};
+use rand::distributions::{Alphanumeric, DistString};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// Account the backend connects with
+pub const DB_USER: &str = "booklore";
+
+/// Length of generated passwords (alphanumeric, safe to embed in SQL literals)
+const PASSWORD_LENGTH: usize = 32;
+
+/// Passwords for the embedded MariaDB server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DbCredentials {
+    /// Password of the `booklore` account used by the backend
+    pub password: String,
+    /// Password of the MariaDB root account, used by the runner for admin tasks
+    pub root_password: String,
+    /// Whether the accounts have been created on the server with these passwords
+    #[serde(default)]
+    pub provisioned: bool,
+}
+
+impl DbCredentials {
+    /// Fresh random passwords, not yet applied to the server
+    pub fn generate() -> Self {
+        Self {
+            password: random_password(),
+            root_password: random_password(),
+            provisioned: false,
+        }
+    }
+}
+
+fn random_password() -> String {
+    Alphanumeric.sample_string(&mut rand::thread_rng(), PASSWORD_LENGTH)
+}
+
+/// Path of config/db-credentials.toml
+pub fn get_credentials_path() -> PathBuf {
+    crate::get_app_data_dir().join("config").join("db-credentials.toml")
+}
+
+/// Read the stored credentials; None if they have not been generated yet
+pub fn load() -> Result<Option<DbCredentials>, String> {
+    load_from(&get_credentials_path())
+}
+
+fn load_from(path: &Path) -> Result<Option<DbCredentials>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read credentials {:?}: {}", path, e))?;
+    let credentials = toml::from_str(&content)
+        .map_err(|e| format!("Failed to parse credentials {:?}: {}", path, e))?;
+    Ok(Some(credentials))
+}
+
+/// Persist the credentials, readable by the current user only
+pub fn save(credentials: &DbCredentials) -> Result<(), String> {
+    let path = get_credentials_path();
+    save_to(credentials, &path)?;
+    info!("Saved database credentials to {:?}", path);
+    Ok(())
+}
+
+fn save_to(credentials: &DbCredentials, path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let content = toml::to_string_pretty(credentials)
+        .map_err(|e| format!("Failed to serialize credentials: {}", e))?;
+
+    crate::config::write_private_file(path, &content)
+        .map_err(|e| format!("Failed to write credentials: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A credentials path in a fresh temporary directory
+    fn credentials_path(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("booklore-credentials-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("config").join("db-credentials.toml")
+    }
+
+    #[test]
+    fn generates_distinct_alphanumeric_passwords() {
+        let credentials = DbCredentials::generate();
+        for password in [&credentials.password, &credentials.root_password] {
+            assert_eq!(password.len(), PASSWORD_LENGTH);
+            assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+        }
+        assert_ne!(credentials.password, credentials.root_password);
+        assert_ne!(credentials.password, DbCredentials::generate().password);
+        assert!(!credentials.provisioned);
+    }
+
+    #[test]
+    fn round_trips_through_the_file() {
+        let path = credentials_path("round-trip");
+        assert_eq!(load_from(&path).unwrap(), None);
+
+        let mut credentials = DbCredentials::generate();
+        credentials.provisioned = true;
+        save_to(&credentials, &path).unwrap();
+        assert_eq!(load_from(&path).unwrap(), Some(credentials));
+
+        std::fs::write(&path, "password = 42").unwrap();
+        assert!(load_from(&path).is_err());
+    }
+
+    #[test]
+    fn older_files_are_not_provisioned() {
+        let path = credentials_path("legacy");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "password = \"a\"\nroot_password = \"b\"\n").unwrap();
+        assert!(!load_from(&path).unwrap().unwrap().provisioned);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn saved_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = credentials_path("private");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        // A world-readable leftover must not pass its mode on
+        std::fs::write(path.with_extension("toml.tmp"), "").unwrap();
+        std::fs::set_permissions(path.with_extension("toml.tmp"), std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        save_to(&DbCredentials::generate(), &path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!path.with_extension("toml.tmp").exists());
+    }
+}
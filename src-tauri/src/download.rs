@@ -0,0 +1,591 @@
+// Download Module
+// Streams runtime archives (JRE, MariaDB) to disk and reports byte-level progress to the loader
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use futures_util::StreamExt;
+use reqwest::StatusCode;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info, warn};
+
+/// Minimum time between two progress events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Progress payload sent to the UI as "download-progress"
+#[derive(Clone, serde::Serialize)]
+pub struct DownloadProgress {
+    /// Startup stage the download belongs to ("jre", "mariadb")
+    pub stage: String,
+    pub downloaded: u64,
+    /// None if the server did not send a Content-Length
+    pub total: Option<u64>,
+    pub bytes_per_sec: u64,
+    pub eta_secs: Option<u64>,
+    pub done: bool,
+}
+
+/// Attempts per download before giving up
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry, doubled on every further attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+
+/// A stalled connection is dropped (and resumed) after this long without data
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An unreachable server fails the attempt after this long instead of hanging the startup
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Why an attempt failed
+enum Failure {
+    /// Worth retrying: network errors, server errors, truncated bodies
+    Transient(String),
+    /// Retrying will not help: missing file, local I/O errors
+    Permanent(String),
+}
+
+/// Download `url` into `dest`, streaming chunk by chunk. Returns the size of the file.
+/// Data is kept in `<dest>.partial` until complete, so an interrupted download resumes
+/// with an HTTP Range request on the next attempt or the next launch. The server's
+/// ETag or Last-Modified is kept next to it and sent as If-Range, so a file that
+/// changed on the server is downloaded again instead of being spliced onto the old one.
+pub async fn download(app: &AppHandle, stage: &str, url: &str, dest: &Path) -> Result<u64, String> {
+    info!("Downloading {} to {:?}", url, dest);
+
+    let client = http_client()?;
+
+    let report = |payload: DownloadProgress| {
+        // Kept for a window that opens while the download is running
+        if let Some(state) = app.try_state::<crate::AppState>() {
+            state.events.record_download(&payload);
+        }
+        if let Err(e) = app.emit("download-progress", payload) {
+            error!("Failed to emit download progress: {}", e);
+        }
+    };
+    let partial = partial_path(dest);
+    let mut attempt = 1;
+    loop {
+        match fetch(&client, &report, stage, url, &partial).await {
+            Ok(size) => {
+                std::fs::rename(&partial, dest)
+                    .map_err(|e| format!("Failed to move download into place: {}", e))?;
+                let _ = std::fs::remove_file(validator_path(&partial));
+                info!("Downloaded {} bytes to {:?}", size, dest);
+                return Ok(size);
+            }
+            Err(Failure::Permanent(e)) => {
+                ProgressReporter::new(&report, stage, 0, None).finish(0);
+                return Err(e);
+            }
+            Err(Failure::Transient(e)) if attempt < MAX_ATTEMPTS => {
+                let delay = INITIAL_BACKOFF.saturating_mul(2u32.pow(attempt - 1));
+                warn!("Download attempt {}/{} failed: {}. Retrying in {}s", attempt, MAX_ATTEMPTS, e, delay.as_secs());
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(Failure::Transient(e)) => {
+                ProgressReporter::new(&report, stage, 0, None).finish(0);
+                return Err(format!("Download failed after {} attempts: {}", MAX_ATTEMPTS, e));
+            }
+        }
+    }
+}
+
+/// One attempt: continue `partial` from where it ends and return its final size
+async fn fetch(client: &reqwest::Client, report: &(dyn Fn(DownloadProgress) + Sync), stage: &str, url: &str, partial: &Path) -> Result<u64, Failure> {
+    let existing = std::fs::metadata(partial).map(|m| m.len()).unwrap_or(0);
+
+    let mut request = client.get(url);
+    if existing > 0 {
+        info!("Resuming download at byte {}", existing);
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", existing));
+        // The server answers with the whole file instead if it changed since the partial was started
+        if let Some(validator) = std::fs::read_to_string(validator_path(partial)).ok().filter(|v| !v.is_empty()) {
+            request = request.header(reqwest::header::IF_RANGE, validator);
+        }
+    }
+    let response = request.send()
+        .await
+        .map_err(|e| Failure::Transient(format!("Failed to download {}: {}", url, e)))?;
+
+    let status = response.status();
+    let (offset, total) = match status {
+        StatusCode::PARTIAL_CONTENT => {
+            let content_range = response.headers()
+                .get(reqwest::header::CONTENT_RANGE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+            // Anything but the bytes right after the partial file would corrupt it
+            if content_range_start(content_range) != Some(existing) {
+                discard_partial(partial);
+                return Err(Failure::Transient(format!(
+                    "Server sent range \"{}\" for a resume at byte {}, starting over", content_range, existing
+                )));
+            }
+            let total = content_range_total(content_range)
+                .or_else(|| response.content_length().map(|len| existing + len));
+            (existing, total)
+        }
+        StatusCode::RANGE_NOT_SATISFIABLE => {
+            // The partial file is already complete, or larger than the file on the server
+            let total = response.headers()
+                .get(reqwest::header::CONTENT_RANGE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(content_range_total);
+            if total == Some(existing) {
+                return Ok(existing);
+            }
+            discard_partial(partial);
+            return Err(Failure::Transient("Partial download does not match the server file, starting over".to_string()));
+        }
+        // No Range support (or a fresh download): start from zero
+        status if status.is_success() => (0, response.content_length()),
+        status if status.is_server_error()
+            || status == StatusCode::REQUEST_TIMEOUT
+            || status == StatusCode::TOO_MANY_REQUESTS =>
+        {
+            return Err(Failure::Transient(format!("Download failed with status: {} - URL: {}", status, url)));
+        }
+        status => {
+            return Err(Failure::Permanent(format!("Download failed with status: {} - URL: {}", status, url)));
+        }
+    };
+    info!("Download size: {} bytes", total.unwrap_or(0));
+    if offset == 0 {
+        save_validator(partial, response.headers());
+    }
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(offset > 0)
+        .truncate(offset == 0)
+        .open(partial)
+        .await
+        .map_err(|e| Failure::Permanent(format!("Failed to create {:?}: {}", partial, e)))?;
+
+    let mut progress = ProgressReporter::new(report, stage, offset, total);
+    let mut downloaded = offset;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                // Keep what arrived so the next attempt resumes from there
+                let _ = file.flush().await;
+                return Err(Failure::Transient(format!("Download interrupted: {}", e)));
+            }
+        };
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| Failure::Permanent(format!("Failed to write {:?}: {}", partial, e)))?;
+        downloaded += chunk.len() as u64;
+        progress.update(downloaded);
+    }
+
+    file.flush()
+        .await
+        .map_err(|e| Failure::Permanent(format!("Failed to write {:?}: {}", partial, e)))?;
+
+    // Validate the size against Content-Length; a short file is resumed, a long one is useless
+    if let Some(total) = total {
+        if downloaded > total {
+            discard_partial(partial);
+        }
+        if downloaded != total {
+            return Err(Failure::Transient(format!(
+                "Download incomplete: got {} of {} bytes", downloaded, total
+            )));
+        }
+    }
+    progress.finish(downloaded);
+    Ok(downloaded)
+}
+
+/// Check a downloaded file against its published SHA-256; a mismatching file is deleted
+pub async fn verify_sha256(path: &Path, expected: &str) -> Result<(), String> {
+    let actual = sha256_of(path).await?;
+
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        let _ = std::fs::remove_file(path);
+        return Err(format!(
+            "Checksum mismatch for {}: expected {}, got {}. The download was discarded, please try again.",
+            path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default(),
+            expected.trim(),
+            actual
+        ));
+    }
+    info!("SHA-256 verified for {:?}", path);
+    Ok(())
+}
+
+/// Hex-encoded SHA-256 of a file, computed off the async runtime
+pub async fn sha256_of(path: &Path) -> Result<String, String> {
+    let file = path.to_path_buf();
+    tokio::task::spawn_blocking(move || sha256_file(&file))
+        .await
+        .map_err(|e| format!("Checksum task failed: {}", e))?
+}
+
+fn sha256_file(path: &Path) -> Result<String, String> {
+    use sha2::{Digest, Sha256};
+
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Find the hash for `file_name` in a sha256sum-style list ("<hash>  <name>" per line)
+pub fn find_checksum(list: &str, file_name: &str) -> Option<String> {
+    list.lines().find_map(|line| {
+        let (hash, name) = line.trim().split_once(char::is_whitespace)?;
+        // sha256sum marks binary mode with a leading '*'
+        let name = name.trim().trim_start_matches('*');
+        (name == file_name || name.ends_with(&format!("/{}", file_name))).then(|| hash.to_string())
+    })
+}
+
+/// HTTP client for release lookups and downloads, with timeouts so a dead network cannot stall the startup
+pub fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::limited(10))
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Fetch a small text document such as a checksum list
+pub async fn fetch_text(url: &str) -> Result<String, String> {
+    let response = http_client()?
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch {}: status {}", url, response.status()));
+    }
+    response.text()
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", url, e))
+}
+
+/// Where a download is kept until it is complete
+fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".partial");
+    dest.with_file_name(name)
+}
+
+/// Where the ETag or Last-Modified of the file behind a partial download is kept
+fn validator_path(partial: &Path) -> PathBuf {
+    let mut name = partial.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".validator");
+    partial.with_file_name(name)
+}
+
+/// Remember what identifies the server's file, for If-Range on a later resume.
+/// A weak ETag is not allowed in If-Range, so Last-Modified is used instead.
+fn save_validator(partial: &Path, headers: &reqwest::header::HeaderMap) {
+    let header = |name| headers.get(name).and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok());
+    let validator = header(reqwest::header::ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(reqwest::header::LAST_MODIFIED));
+
+    let path = validator_path(partial);
+    let result = match validator {
+        Some(validator) => std::fs::write(&path, validator),
+        None => std::fs::remove_file(&path).or_else(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Ok(()),
+            _ => Err(e),
+        }),
+    };
+    if let Err(e) = result {
+        warn!("Failed to update {:?}: {}", path, e);
+    }
+}
+
+/// Drop a partial download that cannot be continued, so the next attempt starts from zero
+fn discard_partial(partial: &Path) {
+    let _ = std::fs::remove_file(partial);
+    let _ = std::fs::remove_file(validator_path(partial));
+}
+
+/// Total size from a Content-Range header ("bytes 100-199/2000" or "bytes */2000")
+fn content_range_total(value: &str) -> Option<u64> {
+    value.rsplit_once('/')?.1.trim().parse().ok()
+}
+
+/// First byte of a Content-Range header ("bytes 100-199/2000"); None for "bytes */2000"
+fn content_range_start(value: &str) -> Option<u64> {
+    value.trim().strip_prefix("bytes ")?.split_once('-')?.0.trim().parse().ok()
+}
+
+/// Directory holding runtime downloads, kept across launches so they can be resumed
+pub fn get_download_dir() -> Result<PathBuf, String> {
+    let dir = crate::get_app_data_dir().join("downloads");
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create download directory: {}", e))?;
+    Ok(dir)
+}
+
+/// Emits progress events no more often than PROGRESS_INTERVAL
+struct ProgressReporter<'a> {
+    report: &'a (dyn Fn(DownloadProgress) + Sync),
+    stage: &'a str,
+    /// Bytes already on disk when this attempt started
+    offset: u64,
+    total: Option<u64>,
+    started: Instant,
+    last_emit: Option<Instant>,
+}
+
+impl<'a> ProgressReporter<'a> {
+    fn new(report: &'a (dyn Fn(DownloadProgress) + Sync), stage: &'a str, offset: u64, total: Option<u64>) -> Self {
+        Self {
+            report,
+            stage,
+            offset,
+            total,
+            started: Instant::now(),
+            last_emit: None,
+        }
+    }
+
+    fn update(&mut self, downloaded: u64) {
+        let now = Instant::now();
+        if self.last_emit.is_some_and(|last| now.duration_since(last) < PROGRESS_INTERVAL) {
+            return;
+        }
+        self.last_emit = Some(now);
+        self.emit(downloaded, false);
+    }
+
+    fn finish(&mut self, downloaded: u64) {
+        self.emit(downloaded, true);
+    }
+
+    fn emit(&self, downloaded: u64, done: bool) {
+        // Average over this attempt; smooth enough for an ETA and immune to bursty chunks
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let transferred = downloaded.saturating_sub(self.offset);
+        let bytes_per_sec = if elapsed > 0.0 { (transferred as f64 / elapsed) as u64 } else { 0 };
+        let eta_secs = match self.total {
+            Some(total) if bytes_per_sec > 0 => Some(total.saturating_sub(downloaded) / bytes_per_sec),
+            _ => None,
+        };
+
+        (self.report)(DownloadProgress {
+            stage: self.stage.to_string(),
+            downloaded,
+            total: self.total,
+            bytes_per_sec,
+            eta_secs,
+            done,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    const SIZE: usize = 1000;
+
+    type Headers = HashMap<String, String>;
+
+    /// What the test server sends back for one request
+    struct Reply {
+        status: &'static str,
+        headers: Vec<(&'static str, String)>,
+        body: Vec<u8>,
+        /// Close the connection after this many body bytes, as a dropped connection would
+        cut_after: Option<usize>,
+    }
+
+    fn file() -> Vec<u8> {
+        (0..SIZE).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// Answer like a server that supports Range and If-Range, for a file with the given ETag
+    fn ranged(request: &Headers, etag: &str) -> Reply {
+        let start = request.get("range")
+            .and_then(|range| range.strip_prefix("bytes="))
+            .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok())
+            .filter(|_| request.get("if-range").is_none_or(|v| v == etag));
+        let etag = ("ETag", etag.to_string());
+        match start {
+            Some(start) if start >= SIZE => Reply {
+                status: "416 Range Not Satisfiable",
+                headers: vec![etag, ("Content-Range", format!("bytes */{}", SIZE))],
+                body: Vec::new(),
+                cut_after: None,
+            },
+            Some(start) => Reply {
+                status: "206 Partial Content",
+                headers: vec![etag, ("Content-Range", format!("bytes {}-{}/{}", start, SIZE - 1, SIZE))],
+                body: file()[start..].to_vec(),
+                cut_after: None,
+            },
+            None => Reply { status: "200 OK", headers: vec![etag], body: file(), cut_after: None },
+        }
+    }
+
+    /// Serve `respond` on a local port; returns the URL and the headers of every request received
+    async fn serve(respond: impl Fn(usize, &Headers) -> Reply + Send + Sync + 'static) -> (String, Arc<Mutex<Vec<Headers>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/runtime.tar.gz", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            let count = AtomicUsize::new(0);
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut head = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !head.ends_with(b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => head.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let headers: Headers = String::from_utf8_lossy(&head)
+                    .lines()
+                    .skip(1)
+                    .filter_map(|line| line.split_once(':'))
+                    .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+                    .collect();
+
+                let reply = respond(count.fetch_add(1, Ordering::SeqCst), &headers);
+                seen.lock().unwrap().push(headers);
+
+                let mut response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", reply.status, reply.body.len());
+                for (name, value) in &reply.headers {
+                    response.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                response.push_str("\r\n");
+                let mut bytes = response.into_bytes();
+                bytes.extend_from_slice(&reply.body[..reply.cut_after.unwrap_or(reply.body.len())]);
+                let _ = socket.write_all(&bytes).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+        (url, requests)
+    }
+
+    /// A fresh partial file path in its own temporary directory
+    fn partial_file(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("booklore-download-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("runtime.tar.gz.partial")
+    }
+
+    async fn attempt(url: &str, partial: &Path) -> Result<u64, Failure> {
+        fetch(&reqwest::Client::new(), &|_| {}, "jre", url, partial).await
+    }
+
+    #[tokio::test]
+    async fn resumes_after_a_dropped_connection() {
+        let (url, requests) = serve(|n, request| {
+            let mut reply = ranged(request, "\"v1\"");
+            if n == 0 {
+                reply.cut_after = Some(400);
+            }
+            reply
+        }).await;
+        let partial = partial_file("resume");
+
+        assert!(matches!(attempt(&url, &partial).await, Err(Failure::Transient(_))));
+        assert_eq!(std::fs::read(&partial).unwrap().len(), 400);
+        assert_eq!(std::fs::read_to_string(validator_path(&partial)).unwrap(), "\"v1\"");
+
+        assert_eq!(attempt(&url, &partial).await.ok(), Some(SIZE as u64));
+        assert_eq!(std::fs::read(&partial).unwrap(), file());
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[1].get("range").map(String::as_str), Some("bytes=400-"));
+        assert_eq!(requests[1].get("if-range").map(String::as_str), Some("\"v1\""));
+    }
+
+    #[tokio::test]
+    async fn starts_over_when_the_server_file_changed() {
+        let (url, _) = serve(|_, request| ranged(request, "\"v2\"")).await;
+        let partial = partial_file("changed");
+        std::fs::write(&partial, vec![0xAA; 400]).unwrap();
+        std::fs::write(validator_path(&partial), "\"v1\"").unwrap();
+
+        assert_eq!(attempt(&url, &partial).await.ok(), Some(SIZE as u64));
+        assert_eq!(std::fs::read(&partial).unwrap(), file());
+        assert_eq!(std::fs::read_to_string(validator_path(&partial)).unwrap(), "\"v2\"");
+    }
+
+    #[tokio::test]
+    async fn starts_over_when_the_range_does_not_follow_the_partial() {
+        let (url, _) = serve(|_, _| Reply {
+            status: "206 Partial Content",
+            headers: vec![("Content-Range", format!("bytes 0-{}/{}", SIZE - 1, SIZE))],
+            body: file(),
+            cut_after: None,
+        }).await;
+        let partial = partial_file("wrong-range");
+        std::fs::write(&partial, &file()[..400]).unwrap();
+
+        assert!(matches!(attempt(&url, &partial).await, Err(Failure::Transient(_))));
+        assert!(!partial.exists());
+    }
+
+    #[tokio::test]
+    async fn accepts_a_complete_partial_on_416() {
+        let (url, _) = serve(|_, request| ranged(request, "\"v1\"")).await;
+        let partial = partial_file("complete");
+        std::fs::write(&partial, file()).unwrap();
+
+        assert_eq!(attempt(&url, &partial).await.ok(), Some(SIZE as u64));
+        assert_eq!(std::fs::read(&partial).unwrap(), file());
+    }
+
+    #[tokio::test]
+    async fn discards_a_partial_larger_than_the_server_file() {
+        let (url, _) = serve(|_, request| ranged(request, "\"v1\"")).await;
+        let partial = partial_file("too-large");
+        std::fs::write(&partial, vec![0; SIZE + 200]).unwrap();
+
+        assert!(matches!(attempt(&url, &partial).await, Err(Failure::Transient(_))));
+        assert!(!partial.exists());
+    }
+
+    #[tokio::test]
+    async fn discards_a_download_larger_than_announced() {
+        // The range claims a 900 byte file but the body runs to 1000
+        let (url, _) = serve(|_, _| Reply {
+            status: "206 Partial Content",
+            headers: vec![("Content-Range", format!("bytes 400-{}/900", SIZE - 1))],
+            body: file()[400..].to_vec(),
+            cut_after: None,
+        }).await;
+        let partial = partial_file("size-mismatch");
+        std::fs::write(&partial, &file()[..400]).unwrap();
+
+        assert!(matches!(attempt(&url, &partial).await, Err(Failure::Transient(_))));
+        assert!(!partial.exists());
+    }
+
+    #[test]
+    fn reads_content_range() {
+        assert_eq!(content_range_total("bytes 100-199/2000"), Some(2000));
+        assert_eq!(content_range_total("bytes */2000"), Some(2000));
+        assert_eq!(content_range_total("bytes 0-99/*"), None);
+        assert_eq!(content_range_start("bytes 100-199/2000"), Some(100));
+        assert_eq!(content_range_start("bytes */2000"), None);
+        assert_eq!(content_range_start(""), None);
+    }
+}
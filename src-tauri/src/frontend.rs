@@ -16,13 +16,16 @@ use futures_util::{SinkExt, StreamExt};
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
+use async_trait::async_trait;
+use tauri::AppHandle;
 use tokio::net::TcpListener;
-use tokio::sync::Mutex;
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message as TungsteniteMessage};
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::ServeDir;
 use tracing::{error, info, debug, warn};
 
+use crate::service::{Service, ServiceHandle};
+
 /// Frontend server state
 #[derive(Clone)]
 pub struct FrontendServerState {
@@ -30,17 +33,39 @@ pub struct FrontendServerState {
     pub frontend_dir: PathBuf,
 }
 
-/// Frontend server handle
-static SERVER_HANDLE: tokio::sync::OnceCell<Mutex<Option<tokio::task::JoinHandle<()>>>> = 
-    tokio::sync::OnceCell::const_new();
+/// The frontend HTTP server as a supervised service
+pub struct FrontendService {
+    frontend_port: u16,
+    backend_port: u16,
+    frontend_dir: PathBuf,
+}
 
-async fn get_handle() -> &'static Mutex<Option<tokio::task::JoinHandle<()>>> {
-    SERVER_HANDLE.get_or_init(|| async { Mutex::new(None) }).await
+impl FrontendService {
+    pub fn new(frontend_port: u16, backend_port: u16, frontend_dir: PathBuf) -> Self {
+        Self {
+            frontend_port,
+            backend_port,
+            frontend_dir,
+        }
+    }
+}
+
+#[async_trait]
+impl Service for FrontendService {
+    fn name(&self) -> &'static str {
+        "frontend"
+    }
+
+    async fn spawn(&self, _app: &AppHandle) -> Result<ServiceHandle, String> {
+        start(self.frontend_port, self.backend_port, self.frontend_dir.clone())
+            .await
+            .map(ServiceHandle::Task)
+    }
 }
 
 /// Start the frontend HTTP server
 /// Serves Angular frontend on specified port and proxies /api to backend
-pub async fn start(frontend_port: u16, backend_port: u16, frontend_dir: PathBuf) -> Result<(), String> {
+async fn start(frontend_port: u16, backend_port: u16, frontend_dir: PathBuf) -> Result<tokio::task::JoinHandle<()>, String> {
     info!("Starting frontend server on port {}...", frontend_port);
     info!("  Frontend directory: {:?}", frontend_dir);
     info!("  Backend port for proxy: {}", backend_port);
@@ -99,17 +124,14 @@ pub async fn start(frontend_port: u16, backend_port: u16, frontend_dir: PathBuf)
     
     info!("Frontend server listening on http://localhost:{}", frontend_port);
     
-    // Store the server handle for graceful shutdown
+    // Hand the server task to the supervisor for shutdown
     let handle = tokio::spawn(async move {
         if let Err(e) = axum::serve(listener, app).await {
             error!("Frontend server error: {}", e);
         }
     });
     
-    let mut guard = get_handle().await.lock().await;
-    *guard = Some(handle);
-    
-    Ok(())
+    Ok(handle)
 }
 
 /// Serve index.html with injected CSS for native macOS header
@@ -137,16 +159,6 @@ async fn serve_index(
     }
 }
 
-/// Stop the frontend server
-pub async fn stop() -> Result<(), String> {
-    let mut guard = get_handle().await.lock().await;
-    if let Some(handle) = guard.take() {
-        handle.abort();
-        info!("Frontend server stopped");
-    }
-    Ok(())
-}
-
 /// Proxy handler for /api/* and /actuator/* requests
 async fn proxy_handler(
     State(state): State<Arc<FrontendServerState>>,
@@ -1,13 +1,18 @@
 // JRE Download and Management Module
 // Handles automatic download of Eclipse Temurin JRE 21 for macOS ARM64
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use serde::Deserialize;
 use tauri::AppHandle;
 use tracing::info;
 
+use crate::config::DownloadsConfig;
+
 const JRE_VERSION: &str = crate::constants::JRE_VERSION;
-const ADOPTIUM_API: &str = crate::constants::ADOPTIUM_API;
+
+/// A JRE is around 150 MB unpacked; anything far beyond that is not a runtime
+const MAX_JRE_UNPACKED_BYTES: u64 = 1024 * 1024 * 1024;
 
 /// Get the JRE installation directory
 fn get_jre_dir() -> PathBuf {
@@ -86,9 +91,10 @@ fn find_system_java() -> Option<String> {
 }
 
 /// Download and install JRE if not present
-pub async fn ensure_jre(app: &AppHandle) -> Result<String, String> {
+pub async fn ensure_jre(app: &AppHandle, sources: &DownloadsConfig) -> Result<String, String> {
     // Check our bundled/downloaded JRE first
     let java_path = get_java_executable();
+    crate::archive::restore_interrupted_replace(&get_jre_dir());
     
     if is_jre_installed() {
         info!("JRE already installed at {:?}", java_path);
@@ -102,8 +108,8 @@ pub async fn ensure_jre(app: &AppHandle) -> Result<String, String> {
         return Ok(system_java);
     }
     
-    info!("No system Java 21+ found, downloading...");
-    download_jre(app).await?;
+    info!("No system Java 21+ found, installing...");
+    download_jre(app, sources).await?;
     
     if is_jre_installed() {
         Ok(java_path.to_string_lossy().to_string())
@@ -112,63 +118,102 @@ pub async fn ensure_jre(app: &AppHandle) -> Result<String, String> {
     }
 }
 
-/// Download JRE from Adoptium
-async fn download_jre(app: &AppHandle) -> Result<(), String> {
-    let jre_dir = get_jre_dir();
-    
-    // Clean up any partial installation
-    if jre_dir.exists() {
-        std::fs::remove_dir_all(&jre_dir)
-            .map_err(|e| format!("Failed to clean JRE directory: {}", e))?;
-    }
-    
-    // Adoptium API URL for macOS ARM64 JRE
-    let download_url = format!(
-        "{}/{}/ga/mac/aarch64/jre/hotspot/normal/eclipse",
-        ADOPTIUM_API, JRE_VERSION
+/// One release package as listed by the Adoptium assets API
+#[derive(Debug, Deserialize)]
+struct AdoptiumAsset {
+    binary: AdoptiumBinary,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdoptiumBinary {
+    package: AdoptiumPackage,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdoptiumPackage {
+    /// SHA-256 of the archive, hex encoded
+    checksum: String,
+    link: String,
+    name: String,
+}
+
+/// Look up the latest macOS ARM64 JRE package together with its published checksum
+async fn find_jre_package(api_url: &str) -> Result<AdoptiumPackage, String> {
+    let url = format!(
+        "{}/assets/latest/{}/hotspot?architecture=aarch64&image_type=jre&os=mac&vendor=eclipse",
+        api_url.trim_end_matches('/'), JRE_VERSION
     );
-    
-    info!("Downloading JRE from: {}", download_url);
-    
-    // Emit download progress
-    crate::emit_status(app, "jre", "active", "Downloading Java runtime...", 45);
-    
-    // Download the archive with redirect support
-    let client = reqwest::Client::builder()
-        .redirect(reqwest::redirect::Policy::limited(10))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
-        
-    let response = client.get(&download_url)
+    let assets: Vec<AdoptiumAsset> = crate::download::http_client()?
+        .get(&url)
         .send()
         .await
-        .map_err(|e| format!("Failed to download JRE: {}", e))?;
-    
-    if !response.status().is_success() {
-        return Err(format!("Download failed with status: {} - URL: {}", response.status(), download_url));
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to look up the Java runtime: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to read the Java runtime release list: {}", e))?;
+
+    assets.into_iter()
+        .map(|asset| asset.binary.package)
+        .find(|package| package.name.ends_with(".tar.gz"))
+        .ok_or_else(|| format!("No Java {} runtime for macOS ARM64 found at Adoptium", JRE_VERSION))
+}
+
+/// Where to download a package: the published link, or the same path below the binary mirror
+fn package_url(link: &str, mirror: &str) -> Result<String, String> {
+    if mirror.is_empty() {
+        return Ok(link.to_string());
     }
+    let link = reqwest::Url::parse(link)
+        .map_err(|e| format!("Invalid Java runtime download link {}: {}", link, e))?;
+    Ok(format!("{}{}", mirror.trim_end_matches('/'), link.path()))
+}
+
+/// Start of the Adoptium JRE archive names for macOS ARM64; the release version follows
+pub fn archive_prefix() -> String {
+    format!("OpenJDK{}U-jre_aarch64_mac", JRE_VERSION)
+}
+
+/// Install the JRE from the offline folder, or download it from Adoptium
+async fn download_jre(app: &AppHandle, sources: &DownloadsConfig) -> Result<(), String> {
+    let jre_dir = get_jre_dir();
     
-    let total_size = response.content_length().unwrap_or(0);
-    info!("Download size: {} bytes", total_size);
+    // Machines without internet access get the archive from the offline folder
+    let archive_prefix = archive_prefix();
+    if let Some(local) = crate::offline::find_archive(&sources.offline_dir, |name| name.starts_with(&archive_prefix))? {
+        crate::emit_status(app, "jre", "active", "Verifying Java runtime from offline folder...", 52);
+        crate::offline::verify(&local).await?;
+        
+        crate::emit_status(app, "jre", "active", "Extracting Java runtime...", 55);
+        // The archive stays where it is; it belongs to the user
+        extract_jre(&local.path, &jre_dir).await?;
+        
+        info!("JRE installed from {:?}", local.path);
+        return Ok(());
+    }
     
-    // Create temp file for download
-    let temp_dir = std::env::temp_dir();
-    let archive_path = temp_dir.join("jre-download.tar.gz");
+    // Resolve the exact package first so the checksum belongs to the file we download
+    let package = find_jre_package(&sources.adoptium_url).await
+        .map_err(|e| format!("{}. Without internet access, place the JRE archive and its checksum in {:?}", e, sources.offline_dir))?;
+    let url = package_url(&package.link, &sources.adoptium_binary_url)?;
+    info!("Downloading JRE {} from: {}", package.name, url);
     
-    let bytes = response.bytes()
-        .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
+    // Emit download progress
+    crate::emit_status(app, "jre", "active", "Downloading Java runtime...", 45);
     
-    std::fs::write(&archive_path, &bytes)
-        .map_err(|e| format!("Failed to write archive: {}", e))?;
+    // Stream the archive to disk; an interrupted download resumes on the next launch
+    let archive_path = crate::download::get_download_dir()?.join(&package.name);
+    crate::download::download(app, "jre", &url, &archive_path).await?;
     
-    info!("Downloaded {} bytes to {:?}", bytes.len(), archive_path);
+    // Never unpack and run an archive that does not match what Adoptium published
+    crate::emit_status(app, "jre", "active", "Verifying Java runtime...", 52);
+    crate::download::verify_sha256(&archive_path, &package.checksum).await?;
     
     // Emit extraction progress
     crate::emit_status(app, "jre", "active", "Extracting Java runtime...", 55);
     
     // Extract the archive
-    extract_jre(&archive_path, &jre_dir)?;
+    extract_jre(&archive_path, &jre_dir).await?;
     
     // Clean up temp file
     let _ = std::fs::remove_file(&archive_path);
@@ -177,52 +222,37 @@ async fn download_jre(app: &AppHandle) -> Result<(), String> {
     Ok(())
 }
 
-/// Extract JRE tar.gz archive
-fn extract_jre(archive_path: &PathBuf, target_dir: &PathBuf) -> Result<(), String> {
-    use flate2::read::GzDecoder;
-    use tar::Archive;
-    
-    let file = std::fs::File::open(archive_path)
-        .map_err(|e| format!("Failed to open archive: {}", e))?;
-    
-    let gz = GzDecoder::new(file);
-    let mut archive = Archive::new(gz);
-    
-    // Create parent directory
+/// Extract the JRE off the async runtime; unpacking takes several seconds
+async fn extract_jre(archive_path: &Path, target_dir: &Path) -> Result<(), String> {
+    let (archive_path, target_dir) = (archive_path.to_path_buf(), target_dir.to_path_buf());
+    tokio::task::spawn_blocking(move || unpack_jre(&archive_path, &target_dir))
+        .await
+        .map_err(|e| format!("Extraction task failed: {}", e))?
+}
+
+/// Extract JRE tar.gz archive and swap it in for the current installation
+fn unpack_jre(archive_path: &Path, target_dir: &Path) -> Result<(), String> {
     let parent = target_dir.parent()
         .ok_or("Invalid target directory")?;
-    std::fs::create_dir_all(parent)
-        .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-    
-    // Extract to temp directory first
-    let temp_extract = parent.join("jre-extract-temp");
-    std::fs::create_dir_all(&temp_extract)
-        .map_err(|e| format!("Failed to create temp directory: {}", e))?;
     
-    archive.unpack(&temp_extract)
-        .map_err(|e| format!("Failed to extract archive: {}", e))?;
+    // Unpack into a private staging directory; it is removed again whatever happens below
+    let staging = crate::archive::extract_tar_gz(archive_path, parent, "jre", MAX_JRE_UNPACKED_BYTES)?;
     
     // Find the extracted JDK directory (has a version in the name)
-    let entries = std::fs::read_dir(&temp_extract)
-        .map_err(|e| format!("Failed to read temp directory: {}", e))?;
+    let entries = std::fs::read_dir(staging.path())
+        .map_err(|e| format!("Failed to read staging directory: {}", e))?;
     
     let jdk_dir = entries
         .filter_map(|e| e.ok())
         .find(|e| e.file_name().to_string_lossy().contains("jdk"))
-        .ok_or("JDK directory not found in archive")?;
-    
-    // Move to final location
-    std::fs::rename(jdk_dir.path(), target_dir)
-        .map_err(|e| format!("Failed to move JRE directory: {}", e))?;
+        .ok_or("JDK directory not found in archive")?
+        .path();
     
-    // Clean up temp directory
-    let _ = std::fs::remove_dir_all(&temp_extract);
-    
-    // Make java executable
+    // Make java executable before the runtime becomes visible
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let java_path = target_dir.join("Contents/Home/bin/java");
+        let java_path = jdk_dir.join("Contents/Home/bin/java");
         if java_path.exists() {
             let mut perms = std::fs::metadata(&java_path)
                 .map_err(|e| format!("Failed to get permissions: {}", e))?
@@ -233,7 +263,8 @@ fn extract_jre(archive_path: &PathBuf, target_dir: &PathBuf) -> Result<(), Strin
         }
     }
     
-    Ok(())
+    // Move to final location in one step, replacing a previous or partial installation
+    crate::archive::replace_dir(&jdk_dir, target_dir)
 }
 
 /// Get JAVA_HOME path
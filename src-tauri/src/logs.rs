@@ -0,0 +1,252 @@
+// Log Capture Module
+// Drains child process output into size-rotated log files and an in-memory buffer of recent lines
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Append-only log file that rotates once it grows past a size limit
+pub struct RotatingLog {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: Option<File>,
+    written: u64,
+}
+
+impl RotatingLog {
+    /// `keep` is the number of rotated files (`name.1` .. `name.N`) to retain
+    pub fn new(path: PathBuf, max_bytes: u64, keep: usize) -> Self {
+        Self {
+            path,
+            max_bytes,
+            keep,
+            file: None,
+            written: 0,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one line, rotating first if the file is full
+    pub fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.file.is_none() {
+            self.open()?;
+        }
+        if self.written >= self.max_bytes {
+            self.rotate()?;
+        }
+
+        let file = self.file.as_mut().expect("log file opened above");
+        file.write_all(line.as_bytes())?;
+        file.write_all(b"\n")?;
+        self.written += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    /// Move the current file to `name.1`, shifting older files up
+    pub fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+
+        if self.keep == 0 {
+            let _ = std::fs::remove_file(&self.path);
+        } else {
+            let _ = std::fs::remove_file(rotated_path(&self.path, self.keep));
+            for i in (1..self.keep).rev() {
+                let from = rotated_path(&self.path, i);
+                if from.exists() {
+                    std::fs::rename(&from, rotated_path(&self.path, i + 1))?;
+                }
+            }
+            if self.path.exists() {
+                std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+            }
+        }
+
+        self.open()
+    }
+
+    fn open(&mut self) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.written = file.metadata().map(|m| m.len()).unwrap_or(0);
+        self.file = Some(file);
+        Ok(())
+    }
+}
+
+/// Path of the n-th rotated file, e.g. `backend.log.2`
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", index));
+    path.with_file_name(name)
+}
+
+/// Destination for captured output: a rotating file plus the most recent lines in memory
+pub struct LogSink {
+    file: Mutex<RotatingLog>,
+    recent: Mutex<VecDeque<String>>,
+    capacity: usize,
+}
+
+impl LogSink {
+    pub fn new(path: PathBuf, max_bytes: u64, keep: usize, capacity: usize) -> Self {
+        Self {
+            file: Mutex::new(RotatingLog::new(path, max_bytes, keep)),
+            recent: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    /// Begin a new run: move a non-empty log aside and forget the buffered lines
+    pub fn start_run(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let has_content = std::fs::metadata(file.path())
+                .map(|m| m.len() > 0)
+                .unwrap_or(false);
+            if has_content {
+                if let Err(e) = file.rotate() {
+                    warn!("Failed to rotate {:?}: {}", file.path(), e);
+                }
+            }
+        }
+
+        if let Ok(mut recent) = self.recent.lock() {
+            recent.clear();
+        }
+    }
+
+    /// Record a line in the file and the ring buffer
+    pub fn push(&self, line: &str) {
+        if let Ok(mut file) = self.file.lock() {
+            if let Err(e) = file.write_line(line) {
+                warn!("Failed to write {:?}: {}", file.path(), e);
+            }
+        }
+
+        if let Ok(mut recent) = self.recent.lock() {
+            if recent.len() == self.capacity {
+                recent.pop_front();
+            }
+            recent.push_back(line.to_string());
+        }
+    }
+
+    /// The last `count` lines, oldest first
+    pub fn tail(&self, count: usize) -> Vec<String> {
+        match self.recent.lock() {
+            Ok(recent) => recent.iter()
+                .skip(recent.len().saturating_sub(count))
+                .cloned()
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Drain a child's stdout or stderr on a background thread until the pipe closes
+    pub fn capture<R: Read + Send + 'static>(self: &Arc<Self>, stream: R) {
+        let sink = self.clone();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(stream);
+            let mut buf = Vec::new();
+            loop {
+                buf.clear();
+                match reader.read_until(b'\n', &mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        let line = String::from_utf8_lossy(&buf);
+                        sink.push(line.trim_end_matches(['\r', '\n']));
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh log path in its own temporary directory
+    fn log_path(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("booklore-logs-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("backend.log")
+    }
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap_or_default()
+    }
+
+    #[test]
+    fn rotates_once_the_size_limit_is_reached() {
+        let path = log_path("rotate");
+        let mut log = RotatingLog::new(path.clone(), 10, 2);
+        log.write_line("first").unwrap();
+        log.write_line("second").unwrap();
+        assert_eq!(read(&path), "first\nsecond\n");
+
+        // 13 bytes written, so the next line starts a new file
+        log.write_line("third").unwrap();
+        assert_eq!(read(&path), "third\n");
+        assert_eq!(read(&rotated_path(&path, 1)), "first\nsecond\n");
+    }
+
+    #[test]
+    fn keeps_only_the_configured_number_of_rotated_files() {
+        let path = log_path("keep");
+        let mut log = RotatingLog::new(path.clone(), 1, 2);
+        for line in ["a", "b", "c", "d"] {
+            log.write_line(line).unwrap();
+        }
+        assert_eq!(read(&path), "d\n");
+        assert_eq!(read(&rotated_path(&path, 1)), "c\n");
+        assert_eq!(read(&rotated_path(&path, 2)), "b\n");
+        assert!(!rotated_path(&path, 3).exists());
+    }
+
+    #[test]
+    fn counts_an_existing_file_towards_the_limit() {
+        let path = log_path("existing");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "0123456789\n").unwrap();
+
+        RotatingLog::new(path.clone(), 10, 1).write_line("new").unwrap();
+        assert_eq!(read(&path), "new\n");
+        assert_eq!(read(&rotated_path(&path, 1)), "0123456789\n");
+    }
+
+    #[test]
+    fn tail_returns_the_newest_lines_oldest_first() {
+        let sink = LogSink::new(log_path("tail"), 1024, 1, 3);
+        for line in ["one", "two", "three", "four"] {
+            sink.push(line);
+        }
+        assert_eq!(sink.tail(2), ["three", "four"]);
+        // Only `capacity` lines are buffered
+        assert_eq!(sink.tail(10), ["two", "three", "four"]);
+    }
+
+    #[test]
+    fn start_run_moves_the_previous_log_aside() {
+        let path = log_path("run");
+        let sink = LogSink::new(path.clone(), 1024, 1, 3);
+        sink.push("old run");
+        sink.start_run();
+        sink.push("new run");
+
+        assert_eq!(sink.tail(10), ["new run"]);
+        assert_eq!(read(&path), "new run\n");
+        assert_eq!(read(&rotated_path(&path, 1)), "old run\n");
+    }
+}
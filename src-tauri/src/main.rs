@@ -9,12 +9,27 @@ mod backend;
 mod tray;
 mod frontend;
 mod constants;
+mod service;
+mod logs;
+mod config;
+mod ports;
+mod credentials;
+mod backup;
+mod mycnf;
+mod recovery;
+mod pidfile;
+mod process;
+mod status;
+mod shutdown;
+mod download;
+mod archive;
+mod offline;
 
 use std::sync::Arc;
 use tauri::{Emitter, Manager, State};
 #[cfg(target_os = "macos")]
 use window_vibrancy::{apply_vibrancy, NSVisualEffectMaterial, NSVisualEffectState};
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, RwLock};
 use tracing::{info, error, Level};
 use tracing_subscriber::FmtSubscriber;
 
@@ -22,25 +37,62 @@ pub use booklore_runner_lib::*;
 
 /// Application state shared across commands
 pub struct AppState {
-    pub mariadb_running: Arc<Mutex<bool>>,
-    pub backend_running: Arc<Mutex<bool>>,
-    pub frontend_running: Arc<Mutex<bool>>,
     pub jre_path: Arc<Mutex<Option<String>>>,
-    pub backend_port: u16,
-    pub frontend_port: u16,
+    pub config: Arc<RwLock<config::RunnerConfig>>,
+    /// Why config/runner.toml could not be loaded; nothing touching the data may run then
+    pub config_error: Option<String>,
     pub is_shutting_down: Arc<std::sync::atomic::AtomicBool>,
+    /// Completed once the shutdown has stopped every service; later and concurrent callers wait on it
+    pub stopped: Arc<tokio::sync::OnceCell<()>>,
+    pub supervisor: Arc<service::Supervisor>,
+    pub backend_logs: Arc<logs::LogSink>,
+    pub mariadb_logs: Arc<logs::LogSink>,
+    /// Startup and shutdown events sent to the UI, for windows that load late
+    pub events: Arc<status::EventLog>,
+    /// Held while services are being launched
+    pub launching: Arc<Mutex<()>>,
+    /// Set while a dump is written or loaded, so backups and restores never overlap
+    pub backup_running: Arc<std::sync::atomic::AtomicBool>,
+    /// Set while a restore has taken over the services
+    pub restoring: Arc<std::sync::atomic::AtomicBool>,
+    /// Tables the crash check found damaged
+    pub damaged_tables: Arc<recovery::DamagedTables>,
 }
 
 impl Default for AppState {
     fn default() -> Self {
+        // The defaults only fill the slot; config_error keeps them from being used
+        let (config, config_error) = match config::load() {
+            Ok(config) => (config, None),
+            Err(e) => {
+                error!("{}", e);
+                (config::RunnerConfig::default(), Some(e))
+            }
+        };
         Self {
-            mariadb_running: Arc::new(Mutex::new(false)),
-            backend_running: Arc::new(Mutex::new(false)),
-            frontend_running: Arc::new(Mutex::new(false)),
             jre_path: Arc::new(Mutex::new(None)),
-            backend_port: constants::BACKEND_PORT,
-            frontend_port: constants::FRONTEND_PORT,
+            config: Arc::new(RwLock::new(config)),
+            config_error,
             is_shutting_down: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            stopped: Arc::new(tokio::sync::OnceCell::new()),
+            supervisor: Arc::new(service::Supervisor::new()),
+            backend_logs: Arc::new(backend::create_log_sink()),
+            mariadb_logs: Arc::new(mariadb::create_log_sink()),
+            events: Arc::new(status::EventLog::default()),
+            launching: Arc::new(Mutex::new(())),
+            backup_running: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            restoring: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            damaged_tables: Arc::new(recovery::DamagedTables::default()),
+        }
+    }
+}
+
+impl AppState {
+    /// The loaded settings, or the reason config/runner.toml was rejected
+    pub async fn settings(&self) -> Result<config::RunnerConfig, String> {
+        match &self.config_error {
+            Some(e) => Err(e.clone()),
+            None => Ok(self.config.read().await.clone()),
         }
     }
 }
@@ -52,27 +104,124 @@ pub struct StartupStatus {
     pub status: String,     // "pending", "active", "complete", "error"
     pub message: String,
     pub progress: u8,
+    /// Position in the event history, so a replaying window can skip events it has seen
+    pub seq: u64,
 }
 
 /// Emit status update to frontend
 fn emit_status(app: &tauri::AppHandle, stage: &str, status: &str, message: &str, progress: u8) {
-    let payload = StartupStatus {
+    send_status(app, "startup-status", stage, status, message, progress);
+}
+
+/// Emit shutdown progress to frontend
+fn emit_shutdown_status(app: &tauri::AppHandle, stage: &str, status: &str, message: &str, progress: u8) {
+    send_status(app, "shutdown-status", stage, status, message, progress);
+}
+
+fn send_status(app: &tauri::AppHandle, event: &'static str, stage: &str, status: &str, message: &str, progress: u8) {
+    let mut payload = StartupStatus {
         stage: stage.to_string(),
         status: status.to_string(),
         message: message.to_string(),
         progress,
+        seq: 0,
     };
+    if let Some(state) = app.try_state::<AppState>() {
+        payload = state.events.record(event, payload);
+    }
     
-    if let Err(e) = app.emit("startup-status", payload) {
+    if let Err(e) = app.emit(event, payload) {
         error!("Failed to emit status: {}", e);
     }
 }
 
-/// Start all services (MariaDB, JRE check, Backend)
 /// Start all services (MariaDB, JRE check, Backend)
 #[tauri::command]
 async fn start_services(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+    // A restore stops and starts the services on its own
+    if state.restoring.load(std::sync::atomic::Ordering::SeqCst) {
+        info!("Restore in progress, leaving service start to it");
+        return Ok(());
+    }
+    // A reloaded window asks again; it picks up the running start through get_status
+    let Ok(launching) = state.launching.clone().try_lock_owned() else {
+        info!("Services are already starting");
+        return Ok(());
+    };
+    launch_locked(app, state, &launching).await
+}
+
+/// Start everything that is not running yet, after any launch in progress
+async fn launch_services(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+    let launching = state.launching.clone().lock_owned().await;
+    launch_locked(app, state, &launching).await
+}
+
+/// Stop everything and start it again. Unlike start_services, a launch or restore in
+/// progress is waited for rather than taken as the result.
+async fn restart_services(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+    let launching = state.launching.clone().lock_owned().await;
+    if let Err(e) = shutdown::stop_services(&app).await {
+        error!("Failed to stop services: {}", e);
+    }
+    launch_locked(app, state, &launching).await
+}
+
+/// The launch itself; the caller's guard keeps other launches out while it runs
+async fn launch_locked(app: tauri::AppHandle, state: State<'_, AppState>, _launching: &tokio::sync::OwnedMutexGuard<()>) -> Result<(), String> {
+    // Restores and repairs relaunch when they finish; after Quit nothing may come back up
+    if state.is_shutting_down.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err("BookLore is shutting down".to_string());
+    }
+    
     info!("Starting BookLore services...");
+    state.events.clear();
+    
+    // Never start on default folders because the settings file has a typo
+    if let Some(e) = &state.config_error {
+        emit_status(&app, "mariadb", "error", e, 0);
+        return Err(e.clone());
+    }
+    
+    // A crashed runner may have left its database or JVM behind, holding the data directory and ports
+    pidfile::terminate_stale_processes().await;
+    
+    // Services that are still up from an earlier attempt keep their ports
+    let mut running = Vec::new();
+    for name in ["mariadb", "backend", "frontend"] {
+        if state.supervisor.is_running(name).await
+            && state.supervisor.state(name).await != service::ServiceState::Failed
+        {
+            running.push(name);
+        }
+    }
+    
+    // Reading the backend JAR for the transport check happens before the config lock is taken
+    let transport = backend::with_transport_fallback(&app, state.settings().await?).await;
+    
+    // Move off any port that another program is using, and remember the choice
+    let config = {
+        let mut stored = state.config.write().await;
+        // Services keep the settings they were started with until the next restart
+        let mut config = stored.clone();
+        // Settings saved meanwhile may turn socket-only off, never on without the check
+        config.mariadb.socket_only &= transport.mariadb.socket_only;
+        match ports::resolve_ports(&mut config, &running) {
+            Ok(true) => {
+                stored.ports = config.ports.clone();
+                if let Err(e) = config::save(&stored) {
+                    error!("Failed to persist selected ports: {}", e);
+                }
+            }
+            Ok(false) => {}
+            Err(e) => {
+                emit_status(&app, "mariadb", "error", &e, 10);
+                return Err(e);
+            }
+        }
+        
+        config
+    };
     
     // Step 1: Start Independent Services (MariaDB, JRE, Frontend) concurrently
     emit_status(&app, "mariadb", "active", "Starting database...", 10);
@@ -86,17 +235,43 @@ async fn start_services(app: tauri::AppHandle, state: State<'_, AppState>) -> Re
         .join("frontend");
 
     // Launch tasks in parallel
-    let mariadb_future = mariadb::start(&app);
-    let jre_future = jre::ensure_jre(&app);
-    let frontend_future = frontend::start(state.frontend_port, state.backend_port, frontend_dir);
+    let supervisor = state.supervisor.clone();
+    let mariadb_future = async {
+        // An external server is only checked, never started or stopped by us
+        if config.external_database.enabled {
+            mariadb::check_external_database(&config.external_database).await.map(Some)
+        } else {
+            let service = Arc::new(mariadb::MariaDbService::new(&config, state.mariadb_logs.clone()));
+            supervisor.start(&app, service).await.map(|_| None)
+        }
+    };
+    let jre_sources = config.download_sources();
+    let jre_future = jre::ensure_jre(&app, &jre_sources);
+    let frontend_future = supervisor.start(
+        &app,
+        Arc::new(frontend::FrontendService::new(config.ports.frontend, config.ports.backend, frontend_dir)),
+    );
     
     let (mariadb_res, jre_res, frontend_res) = tokio::join!(mariadb_future, jre_future, frontend_future);
     
     // Handle MariaDB result
     match mariadb_res {
-        Ok(_) => {
-            *state.mariadb_running.lock().await = true;
+        Ok(Some(external)) => {
+            emit_status(&app, "mariadb", "complete", &format!("Connected to {}", external), 30);
+        }
+        Ok(None) => {
             emit_status(&app, "mariadb", "complete", "Database ready", 30);
+            
+            // Keep the backend away from damaged tables until they are repaired or restored
+            let damaged = state.damaged_tables.list();
+            if !damaged.is_empty() {
+                let message = format!(
+                    "Damaged tables after an unclean shutdown: {}. Repair them or restore a backup.",
+                    damaged.join(", ")
+                );
+                emit_status(&app, "recovery", "error", &message, 30);
+                return Err(message);
+            }
         }
         Err(e) => {
             emit_status(&app, "mariadb", "error", &format!("Database error: {}", e), 30);
@@ -120,8 +295,7 @@ async fn start_services(app: tauri::AppHandle, state: State<'_, AppState>) -> Re
     // Handle Frontend result
     match frontend_res {
         Ok(_) => {
-            *state.frontend_running.lock().await = true;
-            info!("Frontend server started on port {}", state.frontend_port);
+            info!("Frontend server started on port {}", config.ports.frontend);
         }
         Err(e) => {
             error!("Frontend server error: {}", e);
@@ -129,12 +303,17 @@ async fn start_services(app: tauri::AppHandle, state: State<'_, AppState>) -> Re
         }
     }
     
+    // Quit may have been chosen while the database or Java runtime was being prepared
+    if state.is_shutting_down.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err("BookLore is shutting down".to_string());
+    }
+    
     // Step 2: Start Backend (Dependencies ready)
     emit_status(&app, "backend", "active", "Starting BookLore backend...", 70);
     
-    match backend::start(&app, &jre_path, state.backend_port).await {
+    let backend_service = Arc::new(backend::BackendService::new(&jre_path, &config, state.backend_logs.clone()));
+    match supervisor.start(&app, backend_service).await {
         Ok(_) => {
-            *state.backend_running.lock().await = true;
             emit_status(&app, "backend", "complete", "Backend ready", 85);
         }
         Err(e) => {
@@ -144,49 +323,97 @@ async fn start_services(app: tauri::AppHandle, state: State<'_, AppState>) -> Re
     }
     
     emit_status(&app, "backend", "complete", "BookLore is ready!", 100);
-    info!("All services started successfully. Open http://localhost:{}", state.frontend_port);
+    info!("All services started successfully. Open http://localhost:{}", config.ports.frontend);
     Ok(())
 }
 
-/// Stop all services gracefully
+/// Stop all services gracefully, in the same order as a shutdown
 #[tauri::command]
-async fn stop_services(state: State<'_, AppState>) -> Result<(), String> {
-    info!("Stopping BookLore services...");
-    
-    // Stop frontend server first
-    if *state.frontend_running.lock().await {
-        frontend::stop().await?;
-        *state.frontend_running.lock().await = false;
-    }
-    
-    // Stop backend
-    if *state.backend_running.lock().await {
-        backend::stop().await?;
-        *state.backend_running.lock().await = false;
-    }
-    
-    // Then stop MariaDB
-    if *state.mariadb_running.lock().await {
-        mariadb::stop().await?;
-        *state.mariadb_running.lock().await = false;
+async fn stop_services(app: tauri::AppHandle) -> Result<(), String> {
+    shutdown::stop_services(&app).await
+}
+
+/// Service states, shutdown flag and recent events, so a window can render the current state
+#[tauri::command]
+async fn get_status(state: State<'_, AppState>) -> Result<status::StatusSnapshot, String> {
+    Ok(status::StatusSnapshot {
+        services: state.supervisor.snapshot(),
+        starting: state.launching.try_lock().is_err(),
+        shutting_down: state.is_shutting_down.load(std::sync::atomic::Ordering::SeqCst),
+        history: state.events.history(),
+        downloads: state.events.downloads(),
+    })
+}
+
+/// Replace the library database with a backup, given by its file name in the backups directory
+#[tauri::command]
+async fn restore_backup(app: tauri::AppHandle, state: State<'_, AppState>, name: String) -> Result<(), String> {
+    let backups_dir = state.settings().await?.paths.backups_dir;
+    let dump = backup::resolve_backup(&backups_dir, &name)?;
+    backup::restore_backup(&app, &dump).await
+}
+
+/// Repair the tables found damaged after a crash, then finish starting the services
+#[tauri::command]
+async fn repair_database(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+    recovery::repair(&app).await?;
+    launch_services(app, state).await
+}
+
+/// List the file names of the backups, newest first
+#[tauri::command]
+async fn list_backups(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let backups_dir = state.settings().await?.paths.backups_dir;
+    Ok(backup::list_backups(&backups_dir)
+        .into_iter()
+        .filter_map(|backup| Some(backup.path.file_name()?.to_string_lossy().into_owned()))
+        .collect())
+}
+
+/// Get the most recent backend output lines (default: 100)
+#[tauri::command]
+async fn get_backend_logs(lines: Option<usize>, state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    Ok(state.backend_logs.tail(lines.unwrap_or(100)))
+}
+
+/// Get the runner configuration
+#[tauri::command]
+async fn get_config(state: State<'_, AppState>) -> Result<config::RunnerConfig, String> {
+    Ok(state.settings().await?.redacted())
+}
+
+/// Validate and save the runner configuration; applied on the next service restart
+#[tauri::command]
+async fn set_config(mut config: config::RunnerConfig, state: State<'_, AppState>) -> Result<(), String> {
+    // The form was never filled from the file, so saving it would overwrite the user's settings
+    if let Some(e) = &state.config_error {
+        return Err(format!("{}. Fix the file and restart BookLore.", e));
     }
-    
-    info!("All services stopped");
+    config.keep_password(&*state.config.read().await);
+    config::save(&config)?;
+    info!("Runner configuration saved");
+    *state.config.write().await = config;
     Ok(())
 }
 
+/// Get the URL of the BookLore web UI
+#[tauri::command]
+async fn get_ui_url(state: State<'_, AppState>) -> Result<String, String> {
+    Ok(format!("http://localhost:{}", state.settings().await?.ports.frontend))
+}
+
 /// Open BookLore UI in default browser
 #[tauri::command]
 async fn open_ui(state: State<'_, AppState>) -> Result<(), String> {
-    let url = format!("http://localhost:{}", state.frontend_port);
+    let url = get_ui_url(state).await?;
     open::that(&url).map_err(|e| e.to_string())?;
     Ok(())
 }
 
 /// Handle dropped files by copying them to bookdrop directory
 #[tauri::command]
-async fn handle_dropped_files(files: Vec<String>) -> Result<usize, String> {
-    let bookdrop_dir = get_app_data_dir().join("bookdrop");
+async fn handle_dropped_files(files: Vec<String>, state: State<'_, AppState>) -> Result<usize, String> {
+    let bookdrop_dir = state.settings().await?.paths.bookdrop_dir;
     
     // Ensure bookdrop directory exists
     if !bookdrop_dir.exists() {
@@ -252,6 +479,14 @@ fn main() {
             // Setup system tray
             tray::setup(app)?;
             
+            // Scheduled database backups
+            backup::spawn_scheduler(app.handle());
+            
+            // `--restore <file>` recovers the library before the normal startup
+            if let Some(dump) = backup::restore_arg() {
+                backup::begin_restore(app.handle(), dump);
+            }
+            
             // Apply Vibrancy (native blur)
             #[cfg(target_os = "macos")]
             {
@@ -269,6 +504,14 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             start_services,
             stop_services,
+            get_status,
+            restore_backup,
+            repair_database,
+            list_backups,
+            get_backend_logs,
+            get_config,
+            set_config,
+            get_ui_url,
             open_ui,
             handle_dropped_files,
         ])
@@ -279,7 +522,7 @@ fn main() {
             let app_menu = tauri::menu::Submenu::new(handle, "BookLore", true)?;
             let about = tauri::menu::MenuItem::new(handle, "About BookLore", true, None::<&str>)?;
             let separator = tauri::menu::PredefinedMenuItem::separator(handle)?;
-            let settings = tauri::menu::MenuItem::new(handle, "Settings...", true, Some("CmdOrCtrl+,"))?;
+            let settings = tauri::menu::MenuItem::with_id(handle, "settings", "Settings...", true, Some("CmdOrCtrl+,"))?;
             let separator2 = tauri::menu::PredefinedMenuItem::separator(handle)?;
             // Custom Quit Item with ID
             let quit = tauri::menu::MenuItem::with_id(handle, "quit", "Quit BookLore", true, Some("CmdOrCtrl+Q"))?;
@@ -310,7 +553,9 @@ fn main() {
         .on_menu_event(|app, event| {
              let id = event.id();
              if id.as_ref() == "quit" {
-                 trigger_shutdown(app);
+                 shutdown::request(app);
+             } else if id.as_ref() == "settings" {
+                 open_settings(app);
              } else if id.as_ref() == "close" {
                  if let Some(window) = app.get_webview_window("main") {
                      let _ = window.hide();
@@ -335,7 +580,7 @@ fn main() {
                     // Only prevent exit if we haven't started the sequence yet
                     if !state.is_shutting_down.load(std::sync::atomic::Ordering::SeqCst) {
                         api.prevent_exit();
-                        trigger_shutdown(app_handle);
+                        shutdown::request(app_handle);
                     } else {
                          // Allow exit to proceed (this happens when app_handle.exit(0) is called)
                          info!("Allowing exit to proceed");
@@ -349,15 +594,11 @@ fn main() {
                         let _ = window.hide();
                     }
                     
-                    // Safety net: blocking cleanup
+                    // Safety net: returns at once if the shutdown sequence already ran
+                    let app_handle = app_handle.clone();
                     std::thread::spawn(move || {
                         let rt = tokio::runtime::Runtime::new().unwrap();
-                        rt.block_on(async {
-                            info!("Running safety cleanup...");
-                            let _ = backend::stop().await;
-                            let _ = mariadb::stop().await;
-                            let _ = frontend::stop().await;
-                        });
+                        rt.block_on(shutdown::stop_all(&app_handle));
                     }).join().ok();
                 }
                 _ => {}
@@ -365,101 +606,26 @@ fn main() {
         });
 }
 
-/// Trigger the graceful shutdown sequence
-fn trigger_shutdown(app_handle: &tauri::AppHandle) {
-    let state = app_handle.state::<AppState>();
+/// Show the Settings window, creating it on first use
+fn open_settings(app_handle: &tauri::AppHandle) {
+    if let Some(window) = app_handle.get_webview_window("settings") {
+        let _ = window.show();
+        let _ = window.set_focus();
+        return;
+    }
     
-    // Check if we are already shutting down
-    // Atomic check is safe
-    if !state.is_shutting_down.load(std::sync::atomic::Ordering::SeqCst) {
-        state.is_shutting_down.store(true, std::sync::atomic::Ordering::SeqCst);
-        
-        info!("Initiating graceful shutdown sequence...");
-        
-        let app_handle = app_handle.clone();
-        
-        // Show the window if it was hidden
-        if let Some(window) = app_handle.get_webview_window("main") {
-            let _ = window.show();
-            let _ = window.set_focus();
-            
-            // Navigate to loader page to show progress with shutdown flag
-            #[cfg(debug_assertions)]
-            let url = "http://localhost:1420?shutdown=true";
-            #[cfg(not(debug_assertions))]
-            let url = "tauri://localhost?shutdown=true";
-            
-            // We need to navigate back to the wrapper UI
-            let _ = window.eval(&format!("window.location.href = '{}'", url));
-        }
-        
-        // Spawn shutdown task
-        tauri::async_runtime::spawn(async move {
-            // Re-fetch state from owned app handle
-            let state = app_handle.state::<AppState>();
-            // Give UI time to load
-            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-            
-            let _ = app_handle.emit("shutdown-start", ());
-            
-            // 1. Stop Backend
-            let _ = app_handle.emit("shutdown-status", serde_json::json!({
-                "stage": "backend", "status": "active", "message": "Stopping backend...", "progress": 20
-            }));
-            
-            if *state.backend_running.lock().await {
-                if let Err(e) = backend::stop().await {
-                    error!("Failed to stop backend: {}", e);
-                    let _ = app_handle.emit("shutdown-status", serde_json::json!({
-                        "stage": "backend", "status": "error", "message": format!("Error: {}", e)
-                    }));
-                } else {
-                    *state.backend_running.lock().await = false;
-                }
-            }
-            
-            let _ = app_handle.emit("shutdown-status", serde_json::json!({
-                "stage": "backend", "status": "complete", "message": "Backend stopped", "progress": 50
-            }));
-            
-            // 2. Stop MariaDB
-            let _ = app_handle.emit("shutdown-status", serde_json::json!({
-                "stage": "mariadb", "status": "active", "message": "Stopping database...", "progress": 60
-            }));
-            
-            // Mark JRE as skipped/done since we don't manage it explicitly during stop
-            let _ = app_handle.emit("shutdown-status", serde_json::json!({
-                "stage": "jre", "status": "complete", "message": "Runtime stopped", "progress": 60
-            }));
-            
-            if *state.mariadb_running.lock().await {
-                if let Err(e) = mariadb::stop().await {
-                    error!("Failed to stop MariaDB: {}", e);
-                } else {
-                    *state.mariadb_running.lock().await = false;
-                }
-            }
-            
-            let _ = app_handle.emit("shutdown-status", serde_json::json!({
-                "stage": "mariadb", "status": "complete", "message": "Database stopped", "progress": 90
-            }));
-            
-            // 3. Stop Frontend (last)
-            if *state.frontend_running.lock().await {
-                let _ = frontend::stop().await;
-                *state.frontend_running.lock().await = false;
-            }
-            
-            let _ = app_handle.emit("shutdown-status", serde_json::json!({
-                "stage": "backend", "status": "complete", "message": "Goodnight!", "progress": 100
-            }));
-            
-            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-            
-            // Force exit
-            app_handle.exit(0);
-        });
-    } else {
-         info!("Shutdown already in progress");
+    let result = tauri::WebviewWindowBuilder::new(
+        app_handle,
+        "settings",
+        tauri::WebviewUrl::App("settings.html".into()),
+    )
+    .title("BookLore Settings")
+    .inner_size(560.0, 860.0)
+    .resizable(false)
+    .center()
+    .build();
+    
+    if let Err(e) = result {
+        error!("Failed to open settings window: {}", e);
     }
 }
@@ -1,23 +1,130 @@
 // MariaDB Embedded Management Module
 // Handles installation and lifecycle of embedded MariaDB for local database
 
-use std::path::PathBuf;
-use std::process::{Child, Command};
-use std::sync::OnceLock;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
 use tauri::{AppHandle, Manager};
-use tokio::sync::Mutex;
 use tracing::{info, warn, error};
 
-// MariaDB version to use
-// MariaDB version to use
-// Value is now in constants.rs
-const MARIADB_VERSION: &str = crate::constants::MARIADB_VERSION;
+use crate::config::{DownloadsConfig, ExternalDatabaseConfig, RunnerConfig};
+use crate::credentials::DbCredentials;
+use crate::logs::LogSink;
+use crate::service::{Service, ServiceHandle, StopOutcome};
 
-// Store the MariaDB process handle
-static MARIADB_PROCESS: OnceLock<Mutex<Option<Child>>> = OnceLock::new();
+/// The embedded MariaDB server as a supervised service
+pub struct MariaDbService {
+    port: u16,
+    address: DbAddress,
+    data_dir: PathBuf,
+    version: String,
+    /// Mirror and offline folder used when the server has to be installed
+    sources: DownloadsConfig,
+    logs: Arc<LogSink>,
+    /// Set when the previous run did not end with a clean shutdown
+    check_tables: AtomicBool,
+    /// How long InnoDB gets to flush and exit before the server is killed
+    stop_timeout: Duration,
+}
 
-fn get_process_mutex() -> &'static Mutex<Option<Child>> {
-    MARIADB_PROCESS.get_or_init(|| Mutex::new(None))
+impl MariaDbService {
+    pub fn new(config: &RunnerConfig, logs: Arc<LogSink>) -> Self {
+        Self {
+            port: config.ports.mariadb,
+            address: DbAddress::from_config(config),
+            data_dir: config.paths.data_dir.clone(),
+            version: config.mariadb.version.clone(),
+            sources: config.download_sources(),
+            logs,
+            check_tables: AtomicBool::new(false),
+            stop_timeout: Duration::from_secs(config.mariadb.stop_timeout_secs),
+        }
+    }
+}
+
+#[async_trait]
+impl Service for MariaDbService {
+    fn name(&self) -> &'static str {
+        "mariadb"
+    }
+
+    async fn spawn(&self, app: &AppHandle) -> Result<ServiceHandle, String> {
+        // Keep the previous run's log as mariadb.log.1 instead of overwriting it
+        self.logs.start_run();
+        let socket_only = matches!(self.address, DbAddress::Socket(_));
+        let unclean = is_database_initialized(&self.data_dir) && !crate::recovery::had_clean_shutdown(&self.data_dir);
+        let mut child = spawn_mariadb(app, self.port, socket_only, &self.data_dir, &self.version, &self.sources).await?;
+        
+        // The marker is only written back by a clean stop()
+        crate::recovery::clear_clean_shutdown(&self.data_dir);
+        if unclean {
+            warn!("MariaDB was not shut down cleanly last time, tables will be checked");
+        }
+        self.check_tables.store(unclean, Ordering::SeqCst);
+
+        if let Some(stdout) = child.stdout.take() {
+            self.logs.capture(stdout);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            self.logs.capture(stderr);
+        }
+
+        Ok(ServiceHandle::Process(child))
+    }
+
+    async fn wait_ready(&self, app: &AppHandle) -> Result<(), String> {
+        // Wait for socket to be ready
+        wait_for_socket(&self.address, self.port, &self.data_dir, &self.logs).await?;
+        
+        // Replace the empty root password with generated credentials on first start
+        let credentials = ensure_credentials(&self.address).await?;
+        
+        // Bring the system tables up to date after a server upgrade
+        upgrade_data_dir(app, &self.address, &self.data_dir, &credentials).await?;
+        
+        // Create booklore database if not exists
+        create_database(&self.address, &credentials).await?;
+        
+        // Look for damaged tables after a crash, before the backend touches them
+        if self.check_tables.swap(false, Ordering::SeqCst) {
+            crate::recovery::check_after_crash(app, &self.address, &credentials).await?;
+        }
+        
+        info!("MariaDB is ready");
+        Ok(())
+    }
+
+    async fn request_shutdown(&self) -> bool {
+        // Graceful shutdown through the client protocol, falling back to SIGTERM if we cannot log in
+        let root_password = match crate::credentials::load() {
+            Ok(Some(credentials)) if credentials.provisioned => credentials.root_password,
+            _ => String::new(),
+        };
+        match shutdown(&self.address, &root_password).await {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("MariaDB SHUTDOWN failed: {}", e);
+                false
+            }
+        }
+    }
+
+    fn stop_timeout(&self) -> Duration {
+        self.stop_timeout
+    }
+
+    async fn cleanup(&self, outcome: StopOutcome) {
+        // Clean up socket
+        let _ = std::fs::remove_file(get_socket_path());
+        
+        // A killed server leaves InnoDB to crash recovery on the next start
+        if outcome == StopOutcome::Clean {
+            crate::recovery::mark_clean_shutdown(&self.data_dir);
+        }
+    }
 }
 
 /// Get MariaDB installation directory
@@ -25,9 +132,11 @@ fn get_mariadb_dir() -> PathBuf {
     crate::get_app_data_dir().join("mariadb")
 }
 
-/// Get MariaDB data directory
-fn get_data_dir() -> PathBuf {
-    crate::get_app_data_dir().join("data")
+/// Get a MariaDB client tool such as `mariadb-dump`, preferring the system installation
+pub fn get_tool_path(name: &str) -> PathBuf {
+    get_system_mariadb_dir()
+        .map(|d| d.join("bin").join(name))
+        .unwrap_or_else(|| get_mariadb_dir().join("bin").join(name))
 }
 
 /// Get MariaDB socket path
@@ -128,83 +237,67 @@ fn is_mariadb_installed() -> bool {
 }
 
 /// Check if database is initialized
-fn is_database_initialized() -> bool {
-    get_data_dir().join("mysql").exists()
-}
-
-/// Kill any stale MariaDB processes using our data directory
-fn kill_stale_mariadb_processes(data_dir: &std::path::Path) {
-    // Use pgrep to find mariadbd processes
-    let output = Command::new("pgrep")
-        .arg("-f")
-        .arg("mariadbd.*BookLore")
-        .output();
-    
-    if let Ok(out) = output {
-        if out.status.success() {
-            let pids = String::from_utf8_lossy(&out.stdout);
-            for pid_str in pids.trim().lines() {
-                if let Ok(pid) = pid_str.trim().parse::<i32>() {
-                    warn!("Found stale MariaDB process (PID: {}), killing it", pid);
-                    #[cfg(unix)]
-                    unsafe {
-                        libc::kill(pid, libc::SIGTERM);
-                    }
-                }
-            }
-            // Wait a moment for processes to terminate
-            std::thread::sleep(std::time::Duration::from_secs(2));
-        }
-    }
-    
-    // Also check for lock files without active process
-    let aria_lock = data_dir.join("aria_log_control");
-    if aria_lock.exists() {
-        info!("Data directory contains lock files, checking if in use");
-    }
+fn is_database_initialized(data_dir: &Path) -> bool {
+    data_dir.join("mysql").exists()
 }
 
-/// Start MariaDB server
-pub async fn start(app: &AppHandle) -> Result<(), String> {
-    // Check if already running
-    {
-        let guard = get_process_mutex().lock().await;
-        if guard.is_some() {
-            info!("MariaDB already running");
-            return Ok(());
-        }
-    }
-    
+/// Install and initialize MariaDB if needed, then launch the server process
+async fn spawn_mariadb(app: &AppHandle, port: u16, socket_only: bool, data_dir: &Path, version: &str, sources: &DownloadsConfig) -> Result<Child, String> {
     // Ensure MariaDB is installed
-    if !is_mariadb_installed() {
+    crate::archive::restore_interrupted_replace(&get_mariadb_dir());
+    let preinstalled = is_mariadb_installed();
+    if !preinstalled {
         crate::emit_status(app, "mariadb", "active", "Installing database server...", 15);
-        install_mariadb(app).await?;
+        install_mariadb(app, version, sources).await?;
     }
     
+    // Determine correct basedir and binary
+    let (mariadbd_path, basedir) = get_server_paths();
+    let server_version = get_server_version(&mariadbd_path)?;
+    
     // Initialize database if needed
-    if !is_database_initialized() {
+    if !is_database_initialized(data_dir) {
         crate::emit_status(app, "mariadb", "active", "Initializing database...", 20);
-        initialize_database()?;
+        initialize_database(data_dir)?;
+        write_data_version(data_dir, &server_version)?;
     }
     
-    // Start MariaDB
-    crate::emit_status(app, "mariadb", "active", "Starting database server...", 25);
-    
-    let data_dir = get_data_dir();
     let socket_path = get_socket_path();
     
-    // Kill any stale MariaDB processes using our data directory
-    // This can happen if the app crashed without proper cleanup
-    kill_stale_mariadb_processes(&data_dir);
+    // Refuse downgrades and snapshot the data before a newer server touches it
+    check_data_version(app, data_dir, &server_version, preinstalled).await?;
+    
+    // Start MariaDB
+    crate::emit_status(app, "mariadb", "active", "Starting database server...", 25);
     
     // Clean up old socket if exists
     if socket_path.exists() {
         info!("Removing stale socket file");
         let _ = std::fs::remove_file(&socket_path);
     }
-    
-    // Determine correct basedir and binary
-    let (mariadbd_path, basedir) = if let Some(sys_dir) = get_system_mariadb_dir() {
+
+    let defaults_file = crate::mycnf::write_config(&crate::mycnf::ServerPaths {
+        basedir: &basedir,
+        datadir: data_dir,
+        socket: &socket_path,
+        port,
+        socket_only,
+    })?;
+
+    let mut command = Command::new(&mariadbd_path);
+    command
+        .arg(format!("--defaults-file={}", defaults_file.display()))  // Must be the first option
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    crate::process::bind_to_runner(&mut command);
+    command
+        .spawn()
+        .map_err(|e| format!("Failed to start MariaDB: {}", e))
+}
+
+/// The server binary to run and its basedir, preferring the system installation
+fn get_server_paths() -> (PathBuf, PathBuf) {
+    if let Some(sys_dir) = get_system_mariadb_dir() {
         let p = sys_dir.join("bin/mariadbd");
         info!("Using System MariaDB at {:?} with basedir {:?}", p, sys_dir);
         (p, sys_dir)
@@ -213,84 +306,295 @@ pub async fn start(app: &AppHandle) -> Result<(), String> {
         let b = get_mariadb_dir();
         info!("Using Bundled MariaDB at {:?} with basedir {:?}", p, b);
         (p, b)
-    };
+    }
+}
 
-    let log_path = crate::get_app_data_dir().join("mariadb.log");
-    info!("Redirecting MariaDB logs to {:?}", log_path);
-    
-    let log_file = std::fs::File::create(&log_path)
-        .map_err(|e| format!("Failed to create log file: {}", e))?;
-    let log_stderr = log_file.try_clone()
-        .map_err(|e| format!("Failed to clone log file handle: {}", e))?;
+/// File in the data directory recording the server version that initialized or last upgraded it
+const DATA_VERSION_FILE: &str = "booklore_server_version";
 
-    let child = Command::new(&mariadbd_path)
-        .arg(format!("--basedir={}", basedir.display()))
-        .arg(format!("--datadir={}", data_dir.display()))
-        .arg(format!("--socket={}", socket_path.display()))
-        .arg("--bind-address=127.0.0.1")  // Only localhost, no external access
-        .arg(format!("--port={}", crate::constants::MARIADB_PORT))
-        .arg("--skip-grant-tables")  // Single user mode, no auth needed
-        .stdout(log_file)
-        .stderr(log_stderr)
-        .spawn()
-        .map_err(|e| format!("Failed to start MariaDB: {}", e))?;
-    
-    info!("MariaDB started with PID: {}", child.id());
+/// A MariaDB version such as 11.4.5
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ServerVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ServerVersion {
+    /// Find the first x.y.z in text such as "mariadbd  Ver 11.4.5-MariaDB for osx10.20 on arm64"
+    pub fn parse(text: &str) -> Option<Self> {
+        text.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .find_map(|token| {
+                let mut parts = token.split('.').map(|p| p.parse::<u32>().ok());
+                let (major, minor, patch) = (parts.next()??, parts.next()??, parts.next()??);
+                Some(Self { major, minor, patch })
+            })
+    }
+
+    /// Release series (e.g. 11.4); upgrades between series change the system tables
+    fn series(&self) -> (u32, u32) {
+        (self.major, self.minor)
+    }
+}
+
+impl std::fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Ask the server binary for its version
+fn get_server_version(mariadbd_path: &Path) -> Result<ServerVersion, String> {
+    let output = Command::new(mariadbd_path)
+        .arg("--version")
+        .output()
+        .map_err(|e| format!("Failed to run {:?}: {}", mariadbd_path, e))?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    ServerVersion::parse(&text)
+        .ok_or_else(|| format!("Cannot determine the MariaDB version from '{}'", text.trim()))
+}
+
+/// Version the data directory was last used with; older installs only have
+/// the upgrade info that mariadb-upgrade leaves behind
+fn read_data_version(data_dir: &Path) -> Option<ServerVersion> {
+    [DATA_VERSION_FILE, "mariadb_upgrade_info", "mysql_upgrade_info"]
+        .iter()
+        .filter_map(|name| std::fs::read_to_string(data_dir.join(name)).ok())
+        .find_map(|content| ServerVersion::parse(&content))
+}
+
+fn write_data_version(data_dir: &Path, version: &ServerVersion) -> Result<(), String> {
+    std::fs::write(data_dir.join(DATA_VERSION_FILE), format!("{}\n", version))
+        .map_err(|e| format!("Failed to record database version: {}", e))
+}
+
+/// What a data directory needs before `server` may run on it
+#[derive(Debug, PartialEq, Eq)]
+enum DataVersionCheck {
+    /// Used with this very server before
+    Current,
+    /// Same series, so the files are compatible; only the recorded version is out of date or missing
+    Record,
+    /// Another series, or no version at all: snapshot, then run mariadb-upgrade once the server is up
+    Upgrade,
+    /// Written by a newer series, which this server may not be able to read
+    Downgrade(ServerVersion),
+}
+
+/// Compare the version recorded in the data directory (`marker`) or, without one,
+/// found in the upgrade info (`data`) with the server about to run on it
+fn compare_data_version(marker: Option<ServerVersion>, data: Option<ServerVersion>, server: &ServerVersion) -> DataVersionCheck {
+    match data {
+        // Patch releases of a series share the file formats, so only an older series is refused
+        Some(data) if data.series() > server.series() => DataVersionCheck::Downgrade(data),
+        Some(data) if data.series() != server.series() => DataVersionCheck::Upgrade,
+        Some(_) if marker == Some(*server) => DataVersionCheck::Current,
+        Some(_) => DataVersionCheck::Record,
+        None => DataVersionCheck::Upgrade,
+    }
+}
+
+/// Version of the data directory, as far as it can be told. Before the runner kept a marker, a
+/// data directory without upgrade info was last used by the server that is still installed;
+/// only a server installed during this launch may be a different one.
+fn known_data_version(recorded: Option<ServerVersion>, server: &ServerVersion, server_preinstalled: bool) -> Option<ServerVersion> {
+    recorded.or(server_preinstalled.then_some(*server))
+}
+
+/// Version recorded by the runner itself, without the upgrade info fallback
+fn read_data_version_marker(data_dir: &Path) -> Option<ServerVersion> {
+    std::fs::read_to_string(data_dir.join(DATA_VERSION_FILE))
+        .ok()
+        .and_then(|content| ServerVersion::parse(&content))
+}
+
+/// Whether the data directory has to go through mariadb-upgrade for this server
+fn needs_upgrade(data_dir: &Path, server: &ServerVersion) -> bool {
+    let check = compare_data_version(read_data_version_marker(data_dir), read_data_version(data_dir), server);
+    check == DataVersionCheck::Upgrade
+}
+
+/// Compare the data directory with the server about to run on it.
+/// Starting an older series is refused; a patch release of the same series only
+/// records its version, and before an upgrade to another series the
+/// data directory is copied next to itself so it can be recovered by hand.
+async fn check_data_version(app: &AppHandle, data_dir: &Path, server: &ServerVersion, server_preinstalled: bool) -> Result<(), String> {
+    let data = known_data_version(read_data_version(data_dir), server, server_preinstalled);
     
-    // Store process handle
-    {
-        let mut guard = get_process_mutex().lock().await;
-        *guard = Some(child);
+    match compare_data_version(read_data_version_marker(data_dir), data, server) {
+        DataVersionCheck::Current => return Ok(()),
+        DataVersionCheck::Record => {
+            info!("Data directory is compatible with MariaDB {}, recording the version", server);
+            return write_data_version(data_dir, server);
+        }
+        DataVersionCheck::Downgrade(data) => {
+            error!("Data directory is from MariaDB {}, server is {}", data, server);
+            return Err(format!(
+                "The database was last used with MariaDB {}, which is newer than the installed MariaDB {}. \
+                Downgrading is not supported: install MariaDB {} or newer to open it.",
+                data, server, data
+            ));
+        }
+        DataVersionCheck::Upgrade => {}
     }
     
-    // Wait for socket to be ready
-    wait_for_socket(&socket_path).await?;
+    let from = data.map(|v| v.to_string()).unwrap_or_else(|| "unknown".to_string());
+    let snapshot = upgrade_snapshot_path(data_dir, &from);
     
-    // Create booklore database if not exists
-    create_database().await?;
+    info!("Data directory needs an upgrade from MariaDB {} to {}", from, server);
+    if snapshot.exists() {
+        info!("Keeping existing pre-upgrade snapshot {:?}", snapshot);
+        return Ok(());
+    }
+    
+    crate::emit_status(app, "mariadb", "active", "Backing up database before upgrading MariaDB...", 22);
+    let (source, target) = (data_dir.to_path_buf(), snapshot.clone());
+    tokio::task::spawn_blocking(move || copy_dir_recursive(&source, &target))
+        .await
+        .map_err(|e| format!("Snapshot task failed: {}", e))?
+        .map_err(|e| {
+            let _ = std::fs::remove_dir_all(&snapshot);
+            format!("Failed to snapshot the database before upgrading: {}", e)
+        })?;
     
-    info!("MariaDB is ready");
+    info!("Pre-upgrade snapshot saved to {:?}", snapshot);
     Ok(())
 }
 
-/// Stop MariaDB server
-pub async fn stop() -> Result<(), String> {
-    let mut guard = get_process_mutex().lock().await;
+/// Run mariadb-upgrade against the running server and record the new version
+async fn upgrade_data_dir(app: &AppHandle, address: &DbAddress, data_dir: &Path, credentials: &DbCredentials) -> Result<(), String> {
+    let (mariadbd_path, _) = get_server_paths();
+    let server = get_server_version(&mariadbd_path)?;
+    if !needs_upgrade(data_dir, &server) {
+        return Ok(());
+    }
+    let from = read_data_version(data_dir).map(|v| v.to_string()).unwrap_or_else(|| "unknown".to_string());
     
-    if let Some(mut child) = guard.take() {
-        info!("Stopping MariaDB...");
-        
-        // Try graceful shutdown via TCP first
-        let mysql_path = get_system_mariadb_dir()
-            .map(|d| d.join("bin/mariadb"))
-            .unwrap_or_else(|| get_mariadb_dir().join("bin/mariadb"));
-        let _ = Command::new(&mysql_path)
-            .arg("-h")
-            .arg("127.0.0.1")
-            .arg("-P")
-            .arg("13306")
-            .arg("-e")
-            .arg("SHUTDOWN")
-            .output();
-        
-        // Wait a bit for graceful shutdown
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-        
-        // Force kill if still running
-        let _ = child.kill();
-        let _ = child.wait();
-        
-        // Clean up socket
-        let _ = std::fs::remove_file(get_socket_path());
-        
-        info!("MariaDB stopped");
+    crate::emit_status(app, "mariadb", "active", &format!("Upgrading database to MariaDB {}...", server), 28);
+    info!("Running mariadb-upgrade for MariaDB {}", server);
+    
+    let upgrade = get_tool_path("mariadb-upgrade");
+    let root_password = credentials.root_password.clone();
+    let client_args = address.client_args();
+    let output = tokio::task::spawn_blocking(move || {
+        Command::new(&upgrade)
+            .arg("--no-defaults")
+            .args(client_args)
+            .arg("-u")
+            .arg("root")
+            .env("MYSQL_PWD", root_password)
+            .output()
+    })
+    .await
+    .map_err(|e| format!("Upgrade task failed: {}", e))?
+    .map_err(|e| format!("Failed to run mariadb-upgrade: {}", e))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!("mariadb-upgrade failed: {}", stderr.trim());
+        return Err(format!("Upgrading the database to MariaDB {} failed: {}", server, stderr.trim()));
     }
     
+    write_data_version(data_dir, &server)?;
+    info!("Database upgraded to MariaDB {}", server);
+    
+    // Only the copy from right before this upgrade is worth keeping
+    prune_upgrade_snapshots(data_dir, &upgrade_snapshot_path(data_dir, &from));
     Ok(())
 }
 
+/// Where the data directory is copied before upgrading from `from`, next to the original
+fn upgrade_snapshot_path(data_dir: &Path, from: &str) -> PathBuf {
+    data_dir.with_file_name(format!("{}.before-upgrade-{}", dir_name(data_dir), from))
+}
+
+/// Remove the pre-upgrade snapshots of earlier upgrades, keeping `keep`
+fn prune_upgrade_snapshots(data_dir: &Path, keep: &Path) {
+    let Some(parent) = data_dir.parent() else {
+        return;
+    };
+    let prefix = format!("{}.before-upgrade-", dir_name(data_dir));
+    let Ok(entries) = std::fs::read_dir(parent) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path != keep && entry.file_name().to_string_lossy().starts_with(&prefix) {
+            info!("Removing old pre-upgrade snapshot {:?}", path);
+            if let Err(e) = std::fs::remove_dir_all(&path) {
+                warn!("Failed to remove {:?}: {}", path, e);
+            }
+        }
+    }
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// Create the sink that receives mariadbd output (mariadb.log)
+pub fn create_log_sink() -> LogSink {
+    LogSink::new(
+        crate::get_app_data_dir().join("mariadb.log"),
+        crate::constants::LOG_MAX_BYTES,
+        crate::constants::LOG_KEEP_FILES,
+        crate::constants::LOG_BUFFER_LINES,
+    )
+}
+
+/// Messages of the `[ERROR]` lines in mariadbd output, without timestamp and thread id
+pub fn extract_errors(lines: &[String]) -> Vec<String> {
+    lines.iter()
+        .filter_map(|line| line.split_once("[ERROR]"))
+        .map(|(_, message)| message.trim().to_string())
+        .filter(|message| !message.is_empty())
+        .collect()
+}
+
+/// Turn MariaDB error lines into the most likely cause of a failed start
+fn diagnose_errors(errors: &[String], port: u16, data_dir: &Path) -> Option<String> {
+    let find = |needles: &[&str]| {
+        errors.iter().find(|e| needles.iter().any(|n| e.contains(n)))
+    };
+
+    if let Some(e) = find(&["Bind on TCP/IP port", "Address already in use", "another mysqld server running on port"]) {
+        return Some(format!(
+            "Port {} is already in use by another program ({})",
+            port, e
+        ));
+    }
+    if let Some(e) = find(&["Bind on unix socket", "Unable to lock", "mysqld already running"]) {
+        return Some(format!("The database is already in use by another MariaDB process ({})", e));
+    }
+    if let Some(e) = find(&["Permission denied", "Errcode: 13", "error: 13", "OS error: 13"]) {
+        return Some(format!(
+            "MariaDB cannot access its data directory {:?}, check permissions ({})",
+            data_dir, e
+        ));
+    }
+    if let Some(e) = find(&["corrupt", "Corrupt", "Tablespace", "tablespace", "checksum mismatch", "Plugin initialization aborted"]) {
+        return Some(format!("The database files appear to be corrupt ({})", e));
+    }
+    if let Some(e) = find(&["Can't open and lock privilege tables", "Fatal error: Can't open"]) {
+        return Some(format!("The MariaDB system tables are missing or damaged ({})", e));
+    }
+
+    // Fall back to the first real error; "Aborting" only says that it gave up
+    errors.iter()
+        .find(|e| !e.starts_with("Aborting"))
+        .map(|e| e.to_string())
+}
+
+/// Upper bound for the unpacked server tarball, which is well under 1 GB
+const MAX_MARIADB_UNPACKED_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+/// File name of the MariaDB release archive for macOS ARM64, as published and as expected in the offline folder
+pub fn archive_name(version: &str) -> String {
+    format!("mariadb-{}-darwin-arm64.tar.gz", version)
+}
+
 /// Install MariaDB binaries
-async fn install_mariadb(app: &AppHandle) -> Result<(), String> {
+async fn install_mariadb(app: &AppHandle, version: &str, sources: &DownloadsConfig) -> Result<(), String> {
     let mariadb_dir = get_mariadb_dir();
     
     // For now, we expect MariaDB to be bundled with the app
@@ -335,38 +639,44 @@ async fn install_mariadb(app: &AppHandle) -> Result<(), String> {
         return Ok(());
     }
     
+    let file_name = archive_name(version);
+    
+    // Machines without internet access get the archive from the offline folder
+    if let Some(local) = crate::offline::find_archive(&sources.offline_dir, |name| name == file_name)? {
+        crate::emit_status(app, "mariadb", "active", "Verifying database server from offline folder...", 16);
+        crate::offline::verify(&local).await?;
+        
+        crate::emit_status(app, "mariadb", "active", "Extracting database server...", 18);
+        // The archive stays where it is; it belongs to the user
+        extract_mariadb(&local.path, &mariadb_dir).await?;
+        
+        info!("MariaDB installed to {:?} from {:?}", mariadb_dir, local.path);
+        return Ok(());
+    }
+    
     // If not bundled, download (for development)
-    info!("Downloading MariaDB {} for macOS ARM64...", MARIADB_VERSION);
+    info!("Downloading MariaDB {} for macOS ARM64...", version);
     crate::emit_status(app, "mariadb", "active", "Downloading database server...", 15);
     
-    // MariaDB download URL for macOS ARM64
-    let download_url = format!(
-        "https://archive.mariadb.org/mariadb-{}/bintar-darwin-arm64/mariadb-{}-darwin-arm64.tar.gz",
-        MARIADB_VERSION, MARIADB_VERSION
-    );
+    let release_url = format!("{}/mariadb-{}/bintar-darwin-arm64", sources.mariadb_url.trim_end_matches('/'), version);
+    let download_url = format!("{}/{}", release_url, file_name);
     
-    let client = reqwest::Client::new();
-    let response = client.get(&download_url)
-        .send()
+    // The release directory publishes sha256sums.txt next to the tarball
+    let checksums = crate::download::fetch_text(&format!("{}/sha256sums.txt", release_url))
         .await
-        .map_err(|e| format!("Failed to download MariaDB: {}", e))?;
+        .map_err(|e| format!("{}. Without internet access, place {} and its checksum in {:?}", e, file_name, sources.offline_dir))?;
+    let checksum = crate::download::find_checksum(&checksums, &file_name)
+        .ok_or_else(|| format!("No published checksum for {}", file_name))?;
     
-    if !response.status().is_success() {
-        return Err(format!("Download failed with status: {}", response.status()));
-    }
+    let archive_path = crate::download::get_download_dir()?.join(&file_name);
+    crate::download::download(app, "mariadb", &download_url, &archive_path).await?;
     
-    let bytes = response.bytes()
-        .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
+    // Never unpack and run a server binary that does not match the published checksum
+    crate::download::verify_sha256(&archive_path, &checksum).await?;
     
     // Extract archive
-    let temp_dir = std::env::temp_dir();
-    let archive_path = temp_dir.join("mariadb-download.tar.gz");
-    
-    std::fs::write(&archive_path, &bytes)
-        .map_err(|e| format!("Failed to write archive: {}", e))?;
-    
-    extract_mariadb(&archive_path, &mariadb_dir)?;
+    crate::emit_status(app, "mariadb", "active", "Extracting database server...", 18);
+    extract_mariadb(&archive_path, &mariadb_dir).await?;
     
     let _ = std::fs::remove_file(&archive_path);
     
@@ -374,46 +684,37 @@ async fn install_mariadb(app: &AppHandle) -> Result<(), String> {
     Ok(())
 }
 
-/// Extract MariaDB archive
-fn extract_mariadb(archive_path: &PathBuf, target_dir: &PathBuf) -> Result<(), String> {
-    use flate2::read::GzDecoder;
-    use tar::Archive;
-    
-    let file = std::fs::File::open(archive_path)
-        .map_err(|e| format!("Failed to open archive: {}", e))?;
-    
-    let gz = GzDecoder::new(file);
-    let mut archive = Archive::new(gz);
-    
-    let temp_extract = target_dir.parent()
-        .ok_or("Invalid target")?
-        .join("mariadb-extract-temp");
-    
-    std::fs::create_dir_all(&temp_extract)
-        .map_err(|e| format!("Failed to create temp dir: {}", e))?;
+/// Extract MariaDB off the async runtime; the archive is several hundred MB
+async fn extract_mariadb(archive_path: &Path, target_dir: &Path) -> Result<(), String> {
+    let (archive_path, target_dir) = (archive_path.to_path_buf(), target_dir.to_path_buf());
+    tokio::task::spawn_blocking(move || unpack_mariadb(&archive_path, &target_dir))
+        .await
+        .map_err(|e| format!("Extraction task failed: {}", e))?
+}
+
+/// Extract MariaDB archive and swap it in for the current installation
+fn unpack_mariadb(archive_path: &Path, target_dir: &Path) -> Result<(), String> {
+    let parent = target_dir.parent()
+        .ok_or("Invalid target")?;
     
-    archive.unpack(&temp_extract)
-        .map_err(|e| format!("Failed to extract: {}", e))?;
+    // Staging directory is removed again on every path out of this function
+    let staging = crate::archive::extract_tar_gz(archive_path, parent, "mariadb", MAX_MARIADB_UNPACKED_BYTES)?;
     
     // Find extracted directory
-    let entries = std::fs::read_dir(&temp_extract)
-        .map_err(|e| format!("Failed to read temp dir: {}", e))?;
+    let entries = std::fs::read_dir(staging.path())
+        .map_err(|e| format!("Failed to read staging dir: {}", e))?;
     
     let mariadb_extracted = entries
         .filter_map(|e| e.ok())
         .find(|e| e.file_name().to_string_lossy().contains("mariadb"))
-        .ok_or("MariaDB directory not found")?;
-    
-    std::fs::rename(mariadb_extracted.path(), target_dir)
-        .map_err(|e| format!("Failed to move MariaDB: {}", e))?;
-    
-    let _ = std::fs::remove_dir_all(&temp_extract);
+        .ok_or("MariaDB directory not found")?
+        .path();
     
     // Make executables
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let bin_dir = target_dir.join("bin");
+        let bin_dir = mariadb_extracted.join("bin");
         if let Ok(entries) = std::fs::read_dir(&bin_dir) {
             for entry in entries.filter_map(|e| e.ok()) {
                 let mut perms = entry.metadata().map_err(|e| e.to_string())?.permissions();
@@ -423,14 +724,12 @@ fn extract_mariadb(archive_path: &PathBuf, target_dir: &PathBuf) -> Result<(), S
         }
     }
     
-    Ok(())
+    crate::archive::replace_dir(&mariadb_extracted, target_dir)
 }
 
 /// Initialize MariaDB database
-fn initialize_database() -> Result<(), String> {
-    let data_dir = get_data_dir();
-    
-    std::fs::create_dir_all(&data_dir)
+fn initialize_database(data_dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(data_dir)
         .map_err(|e| format!("Failed to create data directory: {}", e))?;
     
     // Prefer system MariaDB if available
@@ -438,7 +737,7 @@ fn initialize_database() -> Result<(), String> {
         let sys_install_db = sys_dir.join("bin/mariadb-install-db");
         if sys_install_db.exists() {
             info!("Using system mariadb-install-db from {:?}", sys_install_db);
-            return run_install_db(&sys_install_db, &sys_dir, &data_dir);
+            return run_install_db(&sys_install_db, &sys_dir, data_dir);
         }
     }
     
@@ -452,12 +751,12 @@ fn initialize_database() -> Result<(), String> {
         // Try alternate location
         let alt_install_db = mariadb_dir.join("bin/mariadb-install-db");
         if alt_install_db.exists() {
-            return run_install_db(&alt_install_db, &mariadb_dir, &data_dir);
+            return run_install_db(&alt_install_db, &mariadb_dir, data_dir);
         }
         return Err("mariadb-install-db not found. Please install MariaDB via Homebrew: brew install mariadb".to_string());
     }
     
-    run_install_db(&install_db, &mariadb_dir, &data_dir)
+    run_install_db(&install_db, &mariadb_dir, data_dir)
 }
 
 fn run_install_db(install_db: &std::path::Path, mariadb_dir: &std::path::Path, data_dir: &std::path::Path) -> Result<(), String> {
@@ -478,40 +777,33 @@ fn run_install_db(install_db: &std::path::Path, mariadb_dir: &std::path::Path, d
     Ok(())
 }
 
-/// Wait for MariaDB to be ready via TCP
-async fn wait_for_socket(_socket_path: &std::path::Path) -> Result<(), String> {
-    info!("Waiting for MariaDB to be ready (TCP port {})", crate::constants::MARIADB_PORT);
+/// Wait for MariaDB to accept connections on its TCP port or socket
+async fn wait_for_socket(address: &DbAddress, port: u16, data_dir: &Path, logs: &LogSink) -> Result<(), String> {
+    info!("Waiting for MariaDB to be ready ({})", address);
     
     for i in 0..60 {
-        // Try to connect via TCP - prefer system mariadb client
-        let mysql_path = get_system_mariadb_dir()
-            .map(|d| d.join("bin/mariadb"))
-            .unwrap_or_else(|| get_mariadb_dir().join("bin/mariadb"));
-        
-        let output = Command::new(&mysql_path)
-            .arg("-h")
-            .arg("127.0.0.1")
-            .arg("-P")
-            .arg(crate::constants::MARIADB_PORT.to_string())
-            .arg("-e")
-            .arg("SELECT 1")
-            .output();
+        // mariadbd logs "[ERROR] Aborting" right before it exits, no point in waiting further
+        let errors = extract_errors(&logs.tail(crate::constants::LOG_BUFFER_LINES));
+        if errors.iter().any(|e| e.starts_with("Aborting")) {
+            let cause = diagnose_errors(&errors, port, data_dir).unwrap_or_else(|| "unknown error".to_string());
+            error!("MariaDB aborted during startup: {}", cause);
+            return Err(format!("MariaDB failed to start: {}", cause));
+        }
         
-        match output {
-            Ok(out) => {
-                if out.status.success() {
-                    info!("MariaDB ready and connection successful");
-                    return Ok(());
-                } else {
-                    let stderr = String::from_utf8_lossy(&out.stderr);
-                    if i % 5 == 0 {
-                        warn!("Attempt {}: Connection failed: {}", i, stderr.trim());
-                    }
-                }
-            },
+        // A rejected login also means the server is up and answering
+        match connect(address, "root", "").await {
+            Ok(conn) => {
+                let _ = conn.disconnect().await;
+                info!("MariaDB ready and connection successful");
+                return Ok(());
+            }
+            Err(DbError::AuthFailed(_)) => {
+                info!("MariaDB ready and accepting connections");
+                return Ok(());
+            }
             Err(e) => {
                 if i % 5 == 0 {
-                    warn!("Attempt {}: Failed to run mysql check: {}", i, e);
+                    warn!("Attempt {}: {}", i, e);
                 }
             }
         }
@@ -519,37 +811,370 @@ async fn wait_for_socket(_socket_path: &std::path::Path) -> Result<(), String> {
         tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
     }
     
-    Err("Timeout waiting for MariaDB to start (TCP connection check failed)".to_string())
+    let errors = extract_errors(&logs.tail(crate::constants::LOG_BUFFER_LINES));
+    match diagnose_errors(&errors, port, data_dir) {
+        Some(cause) => Err(format!("Timeout waiting for MariaDB to start: {}", cause)),
+        None => Err("Timeout waiting for MariaDB to start (TCP connection check failed)".to_string()),
+    }
 }
 
-/// Create booklore database
-async fn create_database() -> Result<(), String> {
-    // Prefer system mariadb client
-    let mysql_path = get_system_mariadb_dir()
-        .map(|d| d.join("bin/mariadb"))
-        .unwrap_or_else(|| get_mariadb_dir().join("bin/mariadb"));
-    
-    let output = Command::new(&mysql_path)
-        .arg("-h")
-        .arg("127.0.0.1")
-        .arg("-P")
-        .arg(crate::constants::MARIADB_PORT.to_string())
-        .arg("-e")
-        .arg("CREATE DATABASE IF NOT EXISTS booklore CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci")
-        .output()
-        .map_err(|e| format!("Failed to create database: {}", e))?;
+/// How clients reach the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DbAddress {
+    /// TCP on 127.0.0.1
+    Tcp(u16),
+    /// Unix socket only, the server does not listen on TCP at all
+    Socket(PathBuf),
+}
+
+impl DbAddress {
+    pub fn from_config(config: &RunnerConfig) -> Self {
+        if config.mariadb.socket_only {
+            DbAddress::Socket(get_socket_path())
+        } else {
+            DbAddress::Tcp(config.ports.mariadb)
+        }
+    }
+
+    /// Connection options for the mariadb command line tools
+    pub fn client_args(&self) -> Vec<String> {
+        match self {
+            DbAddress::Tcp(port) => vec![
+                "-h".to_string(),
+                "127.0.0.1".to_string(),
+                "-P".to_string(),
+                port.to_string(),
+            ],
+            DbAddress::Socket(path) => vec![
+                "--protocol=SOCKET".to_string(),
+                format!("--socket={}", path.display()),
+            ],
+        }
+    }
+}
+
+impl std::fmt::Display for DbAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DbAddress::Tcp(port) => write!(f, "TCP port {}", port),
+            DbAddress::Socket(path) => write!(f, "socket {:?}", path),
+        }
+    }
+}
+
+/// Why a statement could not be run against the server
+#[derive(Debug)]
+pub enum DbError {
+    /// Nothing accepts connections on the port
+    NotListening(String),
+    /// The server rejected the user name or password
+    AuthFailed(String),
+    /// The server is up but still starting or already shutting down
+    Starting(String),
+    /// Connected, but the statement failed
+    Query(String),
+}
+
+impl std::fmt::Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DbError::NotListening(e) => write!(f, "MariaDB is not listening: {}", e),
+            DbError::AuthFailed(e) => write!(f, "MariaDB login failed: {}", e),
+            DbError::Starting(e) => write!(f, "MariaDB is not accepting queries yet: {}", e),
+            DbError::Query(e) => write!(f, "MariaDB query failed: {}", e),
+        }
+    }
+}
+
+impl From<mysql_async::Error> for DbError {
+    fn from(e: mysql_async::Error) -> Self {
+        match e {
+            mysql_async::Error::Io(e) => DbError::NotListening(e.to_string()),
+            mysql_async::Error::Server(e) => match e.code {
+                // ER_DBACCESS_DENIED_ERROR, ER_ACCESS_DENIED_ERROR, ER_ACCESS_DENIED_NO_PASSWORD_ERROR
+                1044 | 1045 | 1698 => DbError::AuthFailed(e.message),
+                // ER_CON_COUNT_ERROR, ER_SERVER_SHUTDOWN, ER_CONNECTION_KILLED
+                1040 | 1053 | 1927 => DbError::Starting(e.message),
+                _ => DbError::Query(format!("{} ({})", e.message, e.code)),
+            },
+            other => DbError::Query(other.to_string()),
+        }
+    }
+}
+
+/// Open a connection to the local server
+async fn connect(address: &DbAddress, user: &str, password: &str) -> Result<mysql_async::Conn, DbError> {
+    let opts = mysql_async::OptsBuilder::default()
+        .user(Some(user))
+        .pass(Some(password))
+        .prefer_socket(false);
+    let opts = match address {
+        DbAddress::Tcp(port) => opts.ip_or_hostname("127.0.0.1").tcp_port(*port),
+        DbAddress::Socket(path) => opts.socket(Some(path.to_string_lossy())),
+    };
     
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        warn!("Create database warning: {}", stderr);
+    open_connection(opts, Duration::from_secs(5)).await
+}
+
+async fn open_connection(opts: mysql_async::OptsBuilder, timeout: Duration) -> Result<mysql_async::Conn, DbError> {
+    match tokio::time::timeout(timeout, mysql_async::Conn::new(opts)).await {
+        Ok(result) => result.map_err(DbError::from),
+        Err(_) => Err(DbError::NotListening("connection timed out".to_string())),
+    }
+}
+
+/// Oldest release series the backend is known to work with
+const MIN_EXTERNAL_MARIADB: (u32, u32) = (10, 6);
+const MIN_EXTERNAL_MYSQL: (u32, u32) = (8, 0);
+
+/// Connect to an external server with the configured account and check its version.
+/// Returns a description such as "MariaDB 11.4.5".
+pub async fn check_external_database(config: &ExternalDatabaseConfig) -> Result<String, String> {
+    use mysql_async::prelude::Queryable;
+    
+    let target = format!("{}:{}", config.host, config.port);
+    info!("Checking external database at {}", target);
+    
+    let opts = mysql_async::OptsBuilder::default()
+        .ip_or_hostname(config.host.trim().trim_start_matches('[').trim_end_matches(']'))
+        .tcp_port(config.port)
+        .user(Some(config.user.clone()))
+        .pass(Some(config.password.clone()))
+        .prefer_socket(false);
+    
+    let mut conn = open_connection(opts, Duration::from_secs(10))
+        .await
+        .map_err(|e| format!("Cannot connect to the external database at {}: {}", target, e))?;
+    let version: Result<Option<String>, _> = conn.query_first("SELECT VERSION()").await;
+    let _ = conn.disconnect().await;
+    
+    let version = version
+        .map_err(|e| format!("External database at {} did not report its version: {}", target, DbError::from(e)))?
+        .unwrap_or_default();
+    let parsed = ServerVersion::parse(&version)
+        .ok_or_else(|| format!("Unrecognized version '{}' of the external database at {}", version, target))?;
+    
+    let (product, minimum) = if version.to_lowercase().contains("mariadb") {
+        ("MariaDB", MIN_EXTERNAL_MARIADB)
+    } else {
+        ("MySQL", MIN_EXTERNAL_MYSQL)
+    };
+    if parsed.series() < minimum {
+        return Err(format!(
+            "The external database at {} runs {} {}, BookLore needs {} {}.{} or newer",
+            target, product, parsed, product, minimum.0, minimum.1
+        ));
+    }
+    
+    info!("External database at {} is {} {}", target, product, parsed);
+    Ok(format!("{} {}", product, parsed))
+}
+
+/// Run statements one after another on a single connection
+async fn execute(address: &DbAddress, user: &str, password: &str, statements: &[String]) -> Result<(), DbError> {
+    use mysql_async::prelude::Queryable;
+    
+    let mut conn = connect(address, user, password).await?;
+    let mut result = Ok(());
+    for statement in statements {
+        if let Err(e) = conn.query_drop(statement.as_str()).await {
+            result = Err(DbError::from(e));
+            break;
+        }
+    }
+    let _ = conn.disconnect().await;
+    result
+}
+
+/// Ask the server to shut down cleanly
+async fn shutdown(address: &DbAddress, password: &str) -> Result<(), DbError> {
+    execute(address, "root", password, &["SHUTDOWN".to_string()]).await
+}
+
+/// Load the database credentials and make sure the server uses them.
+/// Fresh data directories and installs from before authentication was enabled
+/// still have a root account without password; those get locked down here.
+async fn ensure_credentials(address: &DbAddress) -> Result<DbCredentials, String> {
+    let mut credentials = match crate::credentials::load()? {
+        Some(credentials) => credentials,
+        None => {
+            // Saved before touching the server so the passwords can never get lost
+            let credentials = DbCredentials::generate();
+            crate::credentials::save(&credentials)?;
+            credentials
+        }
+    };
+    
+    if credentials.provisioned {
+        return Ok(credentials);
+    }
+    
+    info!("Setting up database accounts");
+    let statements = provision_statements(&credentials, &local_hostname());
+    
+    // A previous attempt may have set the root password but crashed before recording it
+    let result = match execute(address, "root", &credentials.root_password, &statements).await {
+        Err(DbError::AuthFailed(_)) => execute(address, "root", "", &statements).await,
+        result => result,
+    };
+    result.map_err(|e| format!("Failed to set up database accounts: {}", e))?;
+    
+    credentials.provisioned = true;
+    crate::credentials::save(&credentials)?;
+    info!("Database accounts set up");
+    Ok(credentials)
+}
+
+/// Statements that create the backend account and set the root password.
+/// `host` is the machine's hostname, under which mariadb-install-db creates extra accounts.
+fn provision_statements(credentials: &DbCredentials, host: &str) -> Vec<String> {
+    let user = crate::credentials::DB_USER;
+    let password = &credentials.password;
+    let root_password = &credentials.root_password;
+    let host = host.replace('\'', "''");
+
+    // Anonymous accounts, and root reachable under the hostname instead of loopback only
+    let mut unwanted = vec!["''@'localhost'".to_string()];
+    if !["localhost", "127.0.0.1", "::1"].contains(&host.as_str()) {
+        unwanted.push(format!("''@'{host}'"));
+        unwanted.push(format!("'root'@'{host}'"));
+    }
+
+    vec![
+        format!("DROP USER IF EXISTS {}", unwanted.join(", ")),
+        format!("CREATE USER IF NOT EXISTS '{user}'@'localhost' IDENTIFIED BY '{password}'"),
+        format!("CREATE USER IF NOT EXISTS '{user}'@'127.0.0.1' IDENTIFIED BY '{password}'"),
+        format!("ALTER USER '{user}'@'localhost' IDENTIFIED BY '{password}'"),
+        format!("ALTER USER '{user}'@'127.0.0.1' IDENTIFIED BY '{password}'"),
+        format!("GRANT ALL PRIVILEGES ON booklore.* TO '{user}'@'localhost', '{user}'@'127.0.0.1'"),
+        format!("ALTER USER IF EXISTS 'root'@'localhost' IDENTIFIED BY '{root_password}'"),
+        format!("ALTER USER IF EXISTS 'root'@'127.0.0.1' IDENTIFIED BY '{root_password}'"),
+        format!("ALTER USER IF EXISTS 'root'@'::1' IDENTIFIED BY '{root_password}'"),
+        "FLUSH PRIVILEGES".to_string(),
+    ]
+}
+
+/// Hostname of this machine, as mariadb-install-db sees it
+fn local_hostname() -> String {
+    #[cfg(unix)]
+    {
+        let mut buf = [0u8; 256];
+        let ok = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } == 0;
+        if ok {
+            let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+            let name = String::from_utf8_lossy(&buf[..len]).into_owned();
+            if !name.is_empty() {
+                return name;
+            }
+        }
     }
+    "localhost".to_string()
+}
+
+/// Create booklore database
+async fn create_database(address: &DbAddress, credentials: &DbCredentials) -> Result<(), String> {
+    let sql = "CREATE DATABASE IF NOT EXISTS booklore CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci";
+    execute(address, "root", &credentials.root_password, &[sql.to_string()])
+        .await
+        .map_err(|e| format!("Failed to create database: {}", e))?;
     
     info!("booklore database ready");
     Ok(())
 }
 
+/// Run CHECK TABLE on every booklore table and return the ones that are damaged.
+/// `progress` is called with the 1-based position, the table count and the table name.
+pub async fn check_tables(
+    address: &DbAddress,
+    credentials: &DbCredentials,
+    mut progress: impl FnMut(usize, usize, &str),
+) -> Result<Vec<String>, String> {
+    use mysql_async::prelude::Queryable;
+    
+    let mut conn = connect(address, "root", &credentials.root_password)
+        .await
+        .map_err(|e| format!("Failed to check tables: {}", e))?;
+    
+    let result = async {
+        let tables: Vec<String> = conn.query(
+            "SELECT TABLE_NAME FROM information_schema.TABLES \
+            WHERE TABLE_SCHEMA = 'booklore' AND TABLE_TYPE = 'BASE TABLE' ORDER BY TABLE_NAME"
+        ).await?;
+        
+        let mut damaged = Vec::new();
+        for (i, table) in tables.iter().enumerate() {
+            progress(i + 1, tables.len(), table);
+            if !table_status_ok(&mut conn, "CHECK", table).await? {
+                warn!("Table {} is damaged", table);
+                damaged.push(table.clone());
+            }
+        }
+        Ok::<_, mysql_async::Error>(damaged)
+    }.await;
+    
+    let _ = conn.disconnect().await;
+    result.map_err(|e| format!("Failed to check tables: {}", DbError::from(e)))
+}
+
+/// Try to repair damaged tables; returns the ones that are still damaged afterwards
+pub async fn repair_tables(address: &DbAddress, credentials: &DbCredentials, tables: &[String]) -> Result<Vec<String>, String> {
+    use mysql_async::prelude::Queryable;
+    
+    let mut conn = connect(address, "root", &credentials.root_password)
+        .await
+        .map_err(|e| format!("Failed to repair tables: {}", e))?;
+    
+    let result = async {
+        let mut still_damaged = Vec::new();
+        for table in tables {
+            info!("Repairing table {}", table);
+            if !table_status_ok(&mut conn, "REPAIR", table).await? {
+                // InnoDB has no REPAIR TABLE; rebuilding the table recreates its indexes
+                if let Err(e) = conn.query_drop(format!("ALTER TABLE {} FORCE", qualified_table(table))).await {
+                    warn!("Rebuilding table {} failed: {}", table, e);
+                }
+            }
+            if !table_status_ok(&mut conn, "CHECK", table).await? {
+                still_damaged.push(table.clone());
+            }
+        }
+        Ok::<_, mysql_async::Error>(still_damaged)
+    }.await;
+    
+    let _ = conn.disconnect().await;
+    result.map_err(|e| format!("Failed to repair tables: {}", DbError::from(e)))
+}
+
+/// Run CHECK or REPAIR TABLE and tell whether the final status row says OK
+async fn table_status_ok(conn: &mut mysql_async::Conn, operation: &str, table: &str) -> Result<bool, mysql_async::Error> {
+    use mysql_async::prelude::Queryable;
+    
+    // Rows are (Table, Op, Msg_type, Msg_text); the last one carries the verdict
+    let rows: Vec<(String, String, String, String)> = conn
+        .query(format!("{} TABLE {}", operation, qualified_table(table)))
+        .await?;
+    Ok(rows.last().is_some_and(|(_, _, kind, text)| {
+        kind == "status" && (text == "OK" || text == "Table is already up to date")
+    }))
+}
+
+fn qualified_table(table: &str) -> String {
+    format!("`booklore`.`{}`", table.replace('`', "``"))
+}
+
+/// Drop the booklore schema and create it again, empty
+pub async fn recreate_database(address: &DbAddress, credentials: &DbCredentials) -> Result<(), String> {
+    let statements = [
+        "DROP DATABASE IF EXISTS booklore".to_string(),
+        "CREATE DATABASE booklore CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci".to_string(),
+    ];
+    execute(address, "root", &credentials.root_password, &statements)
+        .await
+        .map_err(|e| format!("Failed to recreate database: {}", e))
+}
+
 /// Helper to copy directory recursively
-fn copy_dir_recursive(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
+pub fn copy_dir_recursive(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
     std::fs::create_dir_all(dst)
         .map_err(|e| format!("Failed to create directory: {}", e))?;
     
@@ -568,3 +1193,164 @@ fn copy_dir_recursive(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn extracts_error_messages_without_prefix() {
+        let output = lines(&[
+            "2024-05-01 10:00:00 0 [Note] Starting MariaDB 11.4.2",
+            "2024-05-01 10:00:00 0 [ERROR] Can't start server: Bind on TCP/IP port. Got error: 98: Address already in use",
+            "2024-05-01 10:00:00 0 [ERROR] ",
+            "2024-05-01 10:00:00 0 [ERROR] Aborting",
+        ]);
+        assert_eq!(extract_errors(&output), [
+            "Can't start server: Bind on TCP/IP port. Got error: 98: Address already in use",
+            "Aborting",
+        ]);
+    }
+
+    #[test]
+    fn diagnoses_a_port_conflict() {
+        let errors = lines(&["Can't start server: Bind on TCP/IP port. Got error: 98: Address already in use", "Aborting"]);
+        let cause = diagnose_errors(&errors, 3307, Path::new("/data")).unwrap();
+        assert!(cause.starts_with("Port 3307 is already in use"), "{}", cause);
+    }
+
+    #[test]
+    fn diagnoses_a_locked_datadir() {
+        let errors = lines(&["InnoDB: Unable to lock ./ibdata1 error: 11"]);
+        let cause = diagnose_errors(&errors, 3307, Path::new("/data")).unwrap();
+        assert!(cause.starts_with("The database is already in use"), "{}", cause);
+    }
+
+    #[test]
+    fn diagnoses_permission_and_corruption_errors() {
+        let denied = lines(&["mariadbd: Can't create/write to file '/data/aria_log_control' (Errcode: 13 \"Permission denied\")"]);
+        assert!(diagnose_errors(&denied, 3307, Path::new("/data")).unwrap().contains("check permissions"));
+
+        let corrupt = lines(&["InnoDB: Tablespace 5 was not found at ./booklore/book.ibd", "Plugin 'InnoDB' init function returned error."]);
+        assert!(diagnose_errors(&corrupt, 3307, Path::new("/data")).unwrap().contains("corrupt"));
+    }
+
+    #[test]
+    fn falls_back_to_the_first_real_error() {
+        let errors = lines(&["Aborting", "Unknown option '--foo'"]);
+        assert_eq!(diagnose_errors(&errors, 3307, Path::new("/data")).as_deref(), Some("Unknown option '--foo'"));
+        assert_eq!(diagnose_errors(&lines(&["Aborting"]), 3307, Path::new("/data")), None);
+    }
+
+    #[test]
+    fn provisioning_drops_anonymous_and_hostname_accounts() {
+        let credentials = DbCredentials::generate();
+        let statements = provision_statements(&credentials, "bookshelf.local");
+        assert_eq!(
+            statements[0],
+            "DROP USER IF EXISTS ''@'localhost', ''@'bookshelf.local', 'root'@'bookshelf.local'"
+        );
+        assert!(statements.iter().all(|s| !s.contains("mysql.global_priv")));
+    }
+
+    #[test]
+    fn provisioning_keeps_root_at_localhost() {
+        let credentials = DbCredentials::generate();
+        let statements = provision_statements(&credentials, "localhost");
+        assert_eq!(statements[0], "DROP USER IF EXISTS ''@'localhost'");
+    }
+
+    fn server_error(code: u16) -> DbError {
+        DbError::from(mysql_async::Error::Server(mysql_async::ServerError {
+            code,
+            message: format!("error {}", code),
+            state: "HY000".to_string(),
+        }))
+    }
+
+    #[test]
+    fn maps_access_denied_to_auth_failed() {
+        for code in [1044, 1045, 1698] {
+            assert!(matches!(server_error(code), DbError::AuthFailed(m) if m == format!("error {}", code)), "{}", code);
+        }
+    }
+
+    #[test]
+    fn maps_busy_and_shutting_down_to_starting() {
+        for code in [1040, 1053, 1927] {
+            assert!(matches!(server_error(code), DbError::Starting(_)), "{}", code);
+        }
+    }
+
+    #[test]
+    fn maps_other_errors_to_query() {
+        assert!(matches!(server_error(1146), DbError::Query(m) if m == "error 1146 (1146)"));
+        let io = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "refused");
+        assert!(matches!(DbError::from(mysql_async::Error::Io(io.into())), DbError::NotListening(_)));
+    }
+
+    fn version(text: &str) -> ServerVersion {
+        ServerVersion::parse(text).unwrap()
+    }
+
+    #[test]
+    fn parses_server_versions() {
+        assert_eq!(
+            ServerVersion::parse("mariadbd  Ver 11.4.5-MariaDB for osx10.20 on arm64 (Homebrew)"),
+            Some(ServerVersion { major: 11, minor: 4, patch: 5 })
+        );
+        assert_eq!(ServerVersion::parse("10.11.10-MariaDB\n"), Some(ServerVersion { major: 10, minor: 11, patch: 10 }));
+        assert_eq!(ServerVersion::parse("mariadbd Ver 11.4"), None);
+        assert_eq!(ServerVersion::parse(""), None);
+    }
+
+    #[test]
+    fn orders_versions_numerically() {
+        assert!(version("10.11.2") > version("10.6.20"));
+        assert_eq!(version("11.4.5").series(), (11, 4));
+        assert_eq!(version("11.4.5").series(), version("11.4.2").series());
+        assert_ne!(version("11.4.5").series(), version("10.11.5").series());
+    }
+
+    #[test]
+    fn refuses_older_series() {
+        let server = version("11.4.2");
+        assert_eq!(compare_data_version(None, Some(version("11.8.1")), &server), DataVersionCheck::Downgrade(version("11.8.1")));
+        assert_eq!(compare_data_version(Some(version("12.0.1")), Some(version("12.0.1")), &server), DataVersionCheck::Downgrade(version("12.0.1")));
+        // An older patch release of the same series reads the files just fine
+        assert_eq!(compare_data_version(Some(version("11.4.5")), Some(version("11.4.5")), &server), DataVersionCheck::Record);
+    }
+
+    #[test]
+    fn records_the_version_within_a_series() {
+        let server = version("11.4.5");
+        // Upgrade info from before the marker existed
+        assert_eq!(compare_data_version(None, Some(version("11.4.5")), &server), DataVersionCheck::Record);
+        assert_eq!(compare_data_version(None, Some(version("11.4.2")), &server), DataVersionCheck::Record);
+        assert_eq!(compare_data_version(Some(version("11.4.2")), Some(version("11.4.2")), &server), DataVersionCheck::Record);
+        assert_eq!(compare_data_version(Some(server), Some(server), &server), DataVersionCheck::Current);
+    }
+
+    #[test]
+    fn an_existing_install_without_a_marker_is_only_recorded() {
+        let server = version("11.4.5");
+        // Data directory of an older runner, and the server it always used
+        let data = known_data_version(None, &server, true);
+        assert_eq!(compare_data_version(None, data, &server), DataVersionCheck::Record);
+        // The server was installed just now, so nothing is known about the data
+        assert_eq!(known_data_version(None, &server, false), None);
+        // Upgrade info left by mariadb-upgrade wins over the guess
+        assert_eq!(known_data_version(Some(version("10.11.10")), &server, true), Some(version("10.11.10")));
+    }
+
+    #[test]
+    fn upgrades_across_series_or_without_a_version() {
+        let server = version("11.4.5");
+        assert_eq!(compare_data_version(Some(version("10.11.10")), Some(version("10.11.10")), &server), DataVersionCheck::Upgrade);
+        assert_eq!(compare_data_version(None, None, &server), DataVersionCheck::Upgrade);
+    }
+}
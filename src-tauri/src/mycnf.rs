@@ -0,0 +1,216 @@
+// MariaDB Server Configuration Module
+// Renders config/my.cnf for mariadbd, sized for the machine it runs on
+
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Connections kept free for the runner itself (readiness checks, backups, upgrades)
+const ADMIN_CONNECTIONS: u32 = 10;
+
+/// Bounds for the InnoDB buffer pool in MB; BookLore shares the machine with everything else
+const MIN_BUFFER_POOL_MB: u64 = 128;
+const MAX_BUFFER_POOL_MB: u64 = 1024;
+
+/// Locations and port the server is started with
+pub struct ServerPaths<'a> {
+    pub basedir: &'a Path,
+    pub datadir: &'a Path,
+    pub socket: &'a Path,
+    pub port: u16,
+    /// Listen on the socket only, with no TCP port at all
+    pub socket_only: bool,
+}
+
+/// Path of the generated config/my.cnf
+pub fn get_config_path() -> PathBuf {
+    crate::get_app_data_dir().join("config").join("my.cnf")
+}
+
+/// Path of config/my.override.cnf, included last so its settings win
+pub fn get_override_path() -> PathBuf {
+    crate::get_app_data_dir().join("config").join("my.override.cnf")
+}
+
+/// Write my.cnf (and an empty override file on first run) and return its path
+pub fn write_config(paths: &ServerPaths) -> Result<PathBuf, String> {
+    let path = get_config_path();
+    let override_path = get_override_path();
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    if !override_path.exists() {
+        std::fs::write(&override_path, OVERRIDE_TEMPLATE)
+            .map_err(|e| format!("Failed to create {:?}: {}", override_path, e))?;
+    }
+
+    std::fs::write(&path, render(paths, physical_memory_mb(), &override_path))
+        .map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+
+    info!("MariaDB configuration written to {:?}", path);
+    Ok(path)
+}
+
+/// Render the option file for the given memory size
+fn render(paths: &ServerPaths, memory_mb: Option<u64>, override_path: &Path) -> String {
+    let buffer_pool_mb = buffer_pool_size_mb(memory_mb);
+    // Redo log of a quarter of the buffer pool keeps checkpoints infrequent without slowing recovery
+    let log_file_mb = (buffer_pool_mb / 4).max(48);
+    let max_connections = crate::constants::BACKEND_DB_POOL_SIZE + ADMIN_CONNECTIONS;
+    let networking = if paths.socket_only {
+        "skip-networking".to_string()
+    } else {
+        // Only localhost, no external access
+        format!("port = {}\nbind-address = 127.0.0.1", paths.port)
+    };
+
+    format!(
+        "# Generated by BookLore on every start; changes here are overwritten.\n\
+        # Put your own settings in my.override.cnf instead.\n\
+        \n\
+        [mysqld]\n\
+        basedir = {basedir}\n\
+        datadir = {datadir}\n\
+        socket = {socket}\n\
+        {networking}\n\
+        \n\
+        character-set-server = utf8mb4\n\
+        collation-server = utf8mb4_unicode_ci\n\
+        \n\
+        innodb_buffer_pool_size = {buffer_pool_mb}M\n\
+        innodb_log_file_size = {log_file_mb}M\n\
+        innodb_file_per_table = ON\n\
+        max_connections = {max_connections}\n\
+        performance_schema = OFF\n\
+        \n\
+        [client]\n\
+        default-character-set = utf8mb4\n\
+        \n\
+        !include {override_file}\n",
+        basedir = quote(paths.basedir),
+        datadir = quote(paths.datadir),
+        socket = quote(paths.socket),
+        // MariaDB takes the rest of an !include line as the file name, spaces included,
+        // and does not strip quotes there, so this path is written as it is
+        override_file = override_path.display(),
+    )
+}
+
+/// Starting point for the user's own settings
+const OVERRIDE_TEMPLATE: &str = "# Your MariaDB settings for BookLore.\n\
+# Options here override the generated my.cnf, for example:\n\
+#\n\
+# [mysqld]\n\
+# innodb_buffer_pool_size = 2G\n";
+
+/// An eighth of the RAM, within sensible bounds for a personal library
+fn buffer_pool_size_mb(memory_mb: Option<u64>) -> u64 {
+    match memory_mb {
+        Some(memory) => (memory / 8).clamp(MIN_BUFFER_POOL_MB, MAX_BUFFER_POOL_MB),
+        None => MIN_BUFFER_POOL_MB,
+    }
+}
+
+/// Installed RAM in MB
+fn physical_memory_mb() -> Option<u64> {
+    #[cfg(unix)]
+    {
+        let (pages, page_size) = unsafe {
+            (libc::sysconf(libc::_SC_PHYS_PAGES), libc::sysconf(libc::_SC_PAGESIZE))
+        };
+        if pages > 0 && page_size > 0 {
+            return Some(pages as u64 * page_size as u64 / (1024 * 1024));
+        }
+    }
+
+    warn!("Cannot determine installed memory, using the minimum buffer pool");
+    None
+}
+
+/// Quote a path for an option file; paths like "Application Support" contain spaces
+fn quote(path: &Path) -> String {
+    format!("\"{}\"", path.display().to_string().replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(socket_only: bool) -> ServerPaths<'static> {
+        ServerPaths {
+            basedir: Path::new("/opt/mariadb"),
+            datadir: Path::new("/Users/me/Library/Application Support/BookLore/data"),
+            socket: Path::new("/tmp/booklore.sock"),
+            port: 13306,
+            socket_only,
+        }
+    }
+
+    fn option<'a>(cnf: &'a str, name: &str) -> Option<&'a str> {
+        cnf.lines().find_map(|line| line.strip_prefix(name)?.strip_prefix(" = "))
+    }
+
+    #[test]
+    fn buffer_pool_is_an_eighth_of_memory_within_bounds() {
+        assert_eq!(buffer_pool_size_mb(Some(512)), 128);
+        assert_eq!(buffer_pool_size_mb(Some(4096)), 512);
+        assert_eq!(buffer_pool_size_mb(Some(65536)), 1024);
+        assert_eq!(buffer_pool_size_mb(None), 128);
+    }
+
+    #[test]
+    fn log_file_is_a_quarter_of_the_buffer_pool_but_at_least_48m() {
+        let small = render(&paths(false), Some(1024), Path::new("/cfg/my.override.cnf"));
+        assert_eq!(option(&small, "innodb_buffer_pool_size"), Some("128M"));
+        assert_eq!(option(&small, "innodb_log_file_size"), Some("48M"));
+
+        let large = render(&paths(false), Some(16384), Path::new("/cfg/my.override.cnf"));
+        assert_eq!(option(&large, "innodb_buffer_pool_size"), Some("1024M"));
+        assert_eq!(option(&large, "innodb_log_file_size"), Some("256M"));
+    }
+
+    #[test]
+    fn leaves_room_for_the_runner_connections() {
+        let cnf = render(&paths(false), Some(8192), Path::new("/cfg/my.override.cnf"));
+        let expected = (crate::constants::BACKEND_DB_POOL_SIZE + ADMIN_CONNECTIONS).to_string();
+        assert_eq!(option(&cnf, "max_connections"), Some(expected.as_str()));
+    }
+
+    #[test]
+    fn listens_on_localhost_or_the_socket_only() {
+        let tcp = render(&paths(false), Some(8192), Path::new("/cfg/my.override.cnf"));
+        assert_eq!(option(&tcp, "port"), Some("13306"));
+        assert_eq!(option(&tcp, "bind-address"), Some("127.0.0.1"));
+        assert!(!tcp.contains("skip-networking"));
+
+        let socket = render(&paths(true), Some(8192), Path::new("/cfg/my.override.cnf"));
+        assert!(socket.lines().any(|line| line == "skip-networking"));
+        assert_eq!(option(&socket, "port"), None);
+    }
+
+    #[test]
+    fn quotes_paths_and_includes_the_override_last() {
+        let cnf = render(&paths(false), Some(8192), Path::new("/cfg/my.override.cnf"));
+        assert_eq!(option(&cnf, "datadir"), Some("\"/Users/me/Library/Application Support/BookLore/data\""));
+        assert_eq!(cnf.lines().last(), Some("!include /cfg/my.override.cnf"));
+    }
+
+    #[test]
+    fn includes_an_override_path_with_spaces_verbatim() {
+        let override_path = Path::new("/Users/me/Library/Application Support/BookLore/config/my.override.cnf");
+        let cnf = render(&paths(false), Some(8192), override_path);
+        assert_eq!(
+            cnf.lines().last(),
+            Some("!include /Users/me/Library/Application Support/BookLore/config/my.override.cnf")
+        );
+    }
+
+    #[test]
+    fn escapes_quotes_and_backslashes() {
+        assert_eq!(quote(Path::new("/a b/c")), "\"/a b/c\"");
+        assert_eq!(quote(Path::new("/say \"hi\"")), "\"/say \\\"hi\\\"\"");
+        assert_eq!(quote(Path::new("/back\\slash")), "\"/back\\\\slash\"");
+    }
+}
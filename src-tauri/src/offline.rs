@@ -0,0 +1,170 @@
+// Offline Bundle Module
+// Finds pre-downloaded runtime archives and their checksums, so a first launch works without internet access
+
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::mariadb::ServerVersion;
+
+/// A runtime archive from the offline folder, with the checksum that came with it
+pub struct LocalArchive {
+    pub path: PathBuf,
+    pub checksum: String,
+}
+
+/// Find the newest .tar.gz in `dir` whose file name satisfies `matches`.
+/// An archive without a checksum is an error rather than silently ignored.
+pub fn find_archive(dir: &Path, matches: impl Fn(&str) -> bool) -> Result<Option<LocalArchive>, String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(None);
+    };
+
+    let names: Vec<String> = entries
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".tar.gz") && matches(name))
+        .collect();
+    let Some(name) = newest(names).map_err(|names| {
+        format!("Cannot tell which archive in {:?} is the newest: {}. Keep only one of them.", dir, names.join(", "))
+    })?
+    else {
+        return Ok(None);
+    };
+
+    let checksum = find_local_checksum(dir, &name).ok_or_else(|| {
+        format!("{} in {:?} has no valid checksum. Add {}.sha256 or a sha256sums.txt listing it.", name, dir, name)
+    })?;
+    info!("Found offline archive {} in {:?}", name, dir);
+    Ok(Some(LocalArchive { path: dir.join(name), checksum }))
+}
+
+/// Check the archive against its checksum. Unlike a download, a bad file is left for the user to replace.
+pub async fn verify(archive: &LocalArchive) -> Result<(), String> {
+    let actual = crate::download::sha256_of(&archive.path).await?;
+    if !actual.eq_ignore_ascii_case(&archive.checksum) {
+        return Err(format!(
+            "{:?} does not match its checksum: expected {}, got {}. Replace the file in the offline folder.",
+            archive.path, archive.checksum, actual
+        ));
+    }
+    info!("SHA-256 verified for {:?}", archive.path);
+    Ok(())
+}
+
+/// Pick the archive with the highest version in its name.
+/// Names without a version, or two archives of the same version, are handed back as ambiguous.
+fn newest(names: Vec<String>) -> Result<Option<String>, Vec<String>> {
+    if names.len() <= 1 {
+        return Ok(names.into_iter().next());
+    }
+    let mut versioned: Vec<(Option<ServerVersion>, String)> =
+        names.into_iter().map(|name| (ServerVersion::parse(&name), name)).collect();
+    // Unversioned names sort first, the highest version last
+    versioned.sort();
+    let count = versioned.len();
+    if versioned[0].0.is_none() || versioned[count - 2].0 == versioned[count - 1].0 {
+        return Err(versioned.into_iter().map(|(_, name)| name).collect());
+    }
+    Ok(versioned.pop().map(|(_, name)| name))
+}
+
+/// Checksum from `<archive>.sha256`, `<archive>.sha256.txt` (as Adoptium publishes it) or `sha256sums.txt`
+fn find_local_checksum(dir: &Path, name: &str) -> Option<String> {
+    for suffix in [".sha256", ".sha256.txt"] {
+        if let Ok(content) = std::fs::read_to_string(dir.join(format!("{}{}", name, suffix))) {
+            // Either a bare hash or a sha256sum line; the hash comes first in both
+            return content.split_whitespace().next().filter(|hash| is_sha256(hash)).map(str::to_string);
+        }
+    }
+    let list = std::fs::read_to_string(dir.join("sha256sums.txt")).ok()?;
+    crate::download::find_checksum(&list, name).filter(|hash| is_sha256(hash))
+}
+
+/// A SHA-256 digest written as 64 hex characters
+fn is_sha256(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "3f0e1b6c2a9d4e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f9a8b7c6d5e4f";
+
+    fn offline_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("booklore-offline-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn add_archive(dir: &Path, name: &str) {
+        std::fs::write(dir.join(name), "archive").unwrap();
+        std::fs::write(dir.join(format!("{}.sha256", name)), format!("{}  {}\n", HASH, name)).unwrap();
+    }
+
+    #[test]
+    fn picks_the_highest_version_not_the_last_name() {
+        let dir = offline_dir("newest");
+        let prefix = crate::jre::archive_prefix();
+        add_archive(&dir, &format!("{}_hotspot_21.0.9_10.tar.gz", prefix));
+        add_archive(&dir, &format!("{}_hotspot_21.0.10_7.tar.gz", prefix));
+        add_archive(&dir, &crate::mariadb::archive_name("11.4.5"));
+
+        let local = find_archive(&dir, |name| name.starts_with(&prefix)).unwrap().unwrap();
+        assert_eq!(local.path, dir.join(format!("{}_hotspot_21.0.10_7.tar.gz", prefix)));
+        assert_eq!(local.checksum, HASH);
+        assert!(find_archive(&dir, |name| name.starts_with("missing")).unwrap().is_none());
+    }
+
+    #[test]
+    fn finds_mariadb_only_under_its_exact_name() {
+        let dir = offline_dir("mariadb");
+        let file_name = crate::mariadb::archive_name("11.4.5");
+        assert_eq!(file_name, "mariadb-11.4.5-darwin-arm64.tar.gz");
+
+        // Wrong platform suffix, and another version
+        add_archive(&dir, "mariadb-11.4.5-macos-arm64.tar.gz");
+        add_archive(&dir, "mariadb-11.4.4-darwin-arm64.tar.gz");
+        assert!(find_archive(&dir, |name| name == file_name).unwrap().is_none());
+
+        add_archive(&dir, &file_name);
+        let local = find_archive(&dir, |name| name == file_name).unwrap().unwrap();
+        assert_eq!(local.path, dir.join(&file_name));
+    }
+
+    #[test]
+    fn rejects_archives_it_cannot_order() {
+        let dir = offline_dir("ambiguous");
+        let prefix = crate::jre::archive_prefix();
+        add_archive(&dir, &format!("{}_hotspot_21.0.5_11.tar.gz", prefix));
+        add_archive(&dir, &format!("{}_hotspot_21.0.5_12.tar.gz", prefix));
+        assert!(find_archive(&dir, |name| name.starts_with(&prefix)).is_err());
+
+        assert!(newest(vec!["jre-latest.tar.gz".into(), "jre-21.0.5.tar.gz".into()]).is_err());
+        assert_eq!(newest(vec!["jre-latest.tar.gz".into()]), Ok(Some("jre-latest.tar.gz".into())));
+        assert_eq!(newest(Vec::new()), Ok(None));
+    }
+
+    #[test]
+    fn accepts_only_well_formed_checksums() {
+        let dir = offline_dir("checksum");
+        let name = crate::mariadb::archive_name("11.4.5");
+
+        std::fs::write(dir.join(format!("{}.sha256", name)), HASH).unwrap();
+        assert_eq!(find_local_checksum(&dir, &name).as_deref(), Some(HASH));
+
+        // An HTML error page saved instead of the checksum, or a truncated hash
+        std::fs::write(dir.join(format!("{}.sha256", name)), "<html>Not Found</html>").unwrap();
+        assert_eq!(find_local_checksum(&dir, &name), None);
+        std::fs::write(dir.join(format!("{}.sha256", name)), &HASH[..63]).unwrap();
+        assert_eq!(find_local_checksum(&dir, &name), None);
+
+        std::fs::remove_file(dir.join(format!("{}.sha256", name))).unwrap();
+        std::fs::write(dir.join("sha256sums.txt"), format!("{}  {}\n", HASH.to_uppercase(), name)).unwrap();
+        assert_eq!(find_local_checksum(&dir, &name), Some(HASH.to_uppercase()));
+        std::fs::write(dir.join("sha256sums.txt"), format!("{}z  {}\n", &HASH[..63], name)).unwrap();
+        assert_eq!(find_local_checksum(&dir, &name), None);
+    }
+}
@@ -0,0 +1,343 @@
+// Process Ownership Module
+// Pidfiles for every child process, so leftovers of a crashed runner can be told apart from unrelated processes
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+/// How long a leftover process gets to exit after SIGTERM before it is killed
+const TERMINATE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Contents of `run/<service>.pid`
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct PidFile {
+    /// PID of the child process
+    pid: u32,
+    /// Start time of the child, so a reused PID is not mistaken for it
+    started: u64,
+    /// PID and start time of the runner that spawned it
+    owner_pid: u32,
+    owner_started: u64,
+}
+
+/// Directory holding the pidfiles
+fn get_run_dir() -> PathBuf {
+    crate::get_app_data_dir().join("run")
+}
+
+fn get_pidfile_path(name: &str) -> PathBuf {
+    get_run_dir().join(format!("{}.pid", name))
+}
+
+/// Record a freshly spawned child of this runner
+pub fn write(name: &str, pid: u32) {
+    let (Some(started), Some(owner_started)) = (process_start_time(pid), process_start_time(std::process::id())) else {
+        warn!("Cannot determine start time of {} (PID: {}), not writing a pidfile", name, pid);
+        return;
+    };
+    let pidfile = PidFile {
+        pid,
+        started,
+        owner_pid: std::process::id(),
+        owner_started,
+    };
+
+    let path = get_pidfile_path(name);
+    if let Err(e) = write_to(&path, &pidfile) {
+        warn!("Failed to write pidfile {:?}: {}", path, e);
+    }
+}
+
+fn write_to(path: &Path, pidfile: &PidFile) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let content = toml::to_string(pidfile).map_err(|e| e.to_string())?;
+    std::fs::write(path, content).map_err(|e| e.to_string())
+}
+
+fn read_from(path: &Path) -> Result<PidFile, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    toml::from_str(&content).map_err(|e| e.to_string())
+}
+
+/// Forget a child that has exited
+pub fn remove(name: &str) {
+    let _ = std::fs::remove_file(get_pidfile_path(name));
+}
+
+/// Stop the children a previous, no longer running runner left behind.
+/// Only processes whose PID and start time match a pidfile are touched, apart from
+/// the one-time sweep for runners that predate pidfiles.
+pub async fn terminate_stale_processes() {
+    // Reading the pidfiles and running pgrep block, so the scan runs off the async runtime
+    let leftovers = match tokio::task::spawn_blocking(|| find_stale_processes(&get_run_dir())).await {
+        Ok(leftovers) => leftovers,
+        Err(e) => {
+            warn!("Failed to look for leftover processes: {}", e);
+            return;
+        }
+    };
+
+    for (name, pidfile, path) in leftovers {
+        warn!("Found leftover {} process from a previous run (PID: {}), stopping it", name, pidfile.pid);
+        terminate(&pidfile).await;
+        if let Some(path) = path {
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+    }
+}
+
+/// Leftover processes that are still running, with the pidfile to remove once they are stopped.
+/// Pidfiles of processes that are gone already are removed right away.
+fn find_stale_processes(run_dir: &Path) -> Vec<(String, PidFile, Option<PathBuf>)> {
+    let mut leftovers = Vec::new();
+    let mut recorded = Vec::new();
+
+    for entry in std::fs::read_dir(run_dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("pid") {
+            continue;
+        }
+        let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+
+        let pidfile = match read_from(&path) {
+            Ok(pidfile) => pidfile,
+            Err(e) => {
+                warn!("Ignoring unreadable pidfile {:?}: {}", path, e);
+                let _ = std::fs::remove_file(&path);
+                continue;
+            }
+        };
+        recorded.push(pidfile.pid);
+
+        // Children of a runner that is still alive (this one, or another instance) are not ours to stop
+        if is_same_process(pidfile.owner_pid, pidfile.owner_started) {
+            continue;
+        }
+
+        if !is_same_process(pidfile.pid, pidfile.started) {
+            // Exited, or the PID now belongs to an unrelated process
+            info!("Removing stale pidfile for {} (PID: {})", name, pidfile.pid);
+            let _ = std::fs::remove_file(&path);
+            continue;
+        }
+
+        leftovers.push((name, pidfile, Some(path)));
+    }
+
+    leftovers.extend(find_legacy_processes(run_dir, &recorded));
+    leftovers
+}
+
+/// Command lines of the children spawned by runners from before pidfiles
+const LEGACY_PATTERNS: [(&str, &str); 2] = [("mariadb", "mariadbd.*BookLore"), ("backend", "java.*booklore-api\\.jar")];
+
+/// Written once the legacy sweep has run, so it never runs again
+const LEGACY_SWEEP_MARKER: &str = "legacy-sweep-done";
+
+/// Runners from before pidfiles left no record of their children. On the first start with
+/// pidfiles, orphans (reparented to PID 1) that match their command lines are stopped once,
+/// the way those runners looked for them themselves. Processes in `recorded` are left alone.
+fn find_legacy_processes(run_dir: &Path, recorded: &[u32]) -> Vec<(String, PidFile, Option<PathBuf>)> {
+    let marker = run_dir.join(LEGACY_SWEEP_MARKER);
+    if marker.exists() {
+        return Vec::new();
+    }
+
+    let mut leftovers = Vec::new();
+    for (name, pattern) in LEGACY_PATTERNS {
+        let Ok(output) = std::process::Command::new("pgrep").args(["-P", "1", "-f", pattern]).output() else {
+            continue;
+        };
+        for pid in String::from_utf8_lossy(&output.stdout).lines().filter_map(|line| line.trim().parse::<u32>().ok()) {
+            if recorded.contains(&pid) || pid == std::process::id() {
+                continue;
+            }
+            let Some(started) = process_start_time(pid) else {
+                continue;
+            };
+            let pidfile = PidFile { pid, started, owner_pid: 0, owner_started: 0 };
+            leftovers.push((name.to_string(), pidfile, None));
+        }
+    }
+
+    if let Err(e) = std::fs::create_dir_all(run_dir).and_then(|_| std::fs::write(&marker, "")) {
+        warn!("Failed to record the legacy process sweep: {}", e);
+    }
+    leftovers
+}
+
+/// SIGTERM, then SIGKILL if the process does not exit in time.
+/// Children lead their own process group, so anything they started goes with them.
+async fn terminate(pidfile: &PidFile) {
+    #[cfg(unix)]
+    {
+        let pid = pidfile.pid as i32;
+        let target = if unsafe { libc::getpgid(pid) } == pid { -pid } else { pid };
+        unsafe {
+            libc::kill(target, libc::SIGTERM);
+        }
+
+        let deadline = tokio::time::Instant::now() + TERMINATE_TIMEOUT;
+        while tokio::time::Instant::now() < deadline {
+            if !is_same_process(pidfile.pid, pidfile.started) {
+                info!("Leftover process {} exited", pidfile.pid);
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+
+        // Check once more right before the kill so a reused PID is never hit
+        if is_same_process(pidfile.pid, pidfile.started) {
+            warn!("Leftover process {} ignored SIGTERM, killing it", pidfile.pid);
+            unsafe {
+                libc::kill(target, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+/// Whether `pid` is alive and is still the process that started at `started`
+fn is_same_process(pid: u32, started: u64) -> bool {
+    process_start_time(pid) == Some(started)
+}
+
+/// Start time of a process in an OS-specific unit; None if it is not running
+#[cfg(target_os = "linux")]
+fn process_start_time(pid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name may contain spaces, so count fields after its closing parenthesis:
+    // state is field 3 and starttime field 22
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    // A zombie has exited already, it only waits to be reaped
+    if fields.first() == Some(&"Z") {
+        return None;
+    }
+    fields.get(19)?.parse().ok()
+}
+
+/// Start time of a process in microseconds since the epoch; None if it is not running
+#[cfg(target_os = "macos")]
+fn process_start_time(pid: u32) -> Option<u64> {
+    let mut info: libc::proc_bsdinfo = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<libc::proc_bsdinfo>() as i32;
+    let written = unsafe {
+        libc::proc_pidinfo(
+            pid as i32,
+            libc::PROC_PIDTBSDINFO,
+            0,
+            &mut info as *mut _ as *mut libc::c_void,
+            size,
+        )
+    };
+    (written == size).then(|| info.pbi_start_tvsec * 1_000_000 + info.pbi_start_tvusec)
+}
+
+/// Without a start time there is no proof of ownership, so nothing is ever touched
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn process_start_time(_pid: u32) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pidfile_path(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("booklore-pidfile-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("run").join("mariadb.pid")
+    }
+
+    #[test]
+    fn round_trips_through_the_file() {
+        let path = pidfile_path("round-trip");
+        let pidfile = PidFile { pid: 4242, started: 1_700_000_000_123_456, owner_pid: 4200, owner_started: 1_699_999_999_000_000 };
+        write_to(&path, &pidfile).unwrap();
+        assert_eq!(read_from(&path).unwrap(), pidfile);
+
+        std::fs::write(&path, "pid = \"not a number\"").unwrap();
+        assert!(read_from(&path).is_err());
+    }
+
+    #[test]
+    fn keeps_pidfiles_of_a_live_runner_and_drops_dead_ones() {
+        let path = pidfile_path("scan");
+        let run_dir = path.parent().unwrap().to_path_buf();
+        std::fs::create_dir_all(&run_dir).unwrap();
+        // The legacy sweep has run already
+        std::fs::write(run_dir.join(LEGACY_SWEEP_MARKER), "").unwrap();
+
+        // Owned by this very process, which is alive
+        let Some(own_start) = process_start_time(std::process::id()) else {
+            return;
+        };
+        let alive = PidFile { pid: 1, started: 1, owner_pid: std::process::id(), owner_started: own_start };
+        write_to(&run_dir.join("backend.pid"), &alive).unwrap();
+        // A runner that is gone, whose child is gone as well
+        let dead = PidFile { pid: u32::MAX - 1, started: 1, owner_pid: u32::MAX - 2, owner_started: 1 };
+        write_to(&run_dir.join("mariadb.pid"), &dead).unwrap();
+        std::fs::write(run_dir.join("broken.pid"), "garbage").unwrap();
+
+        assert!(find_stale_processes(&run_dir).is_empty());
+        assert!(run_dir.join("backend.pid").exists());
+        assert!(!run_dir.join("mariadb.pid").exists());
+        assert!(!run_dir.join("broken.pid").exists());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn finds_the_child_of_a_dead_runner() {
+        let path = pidfile_path("orphan");
+        let run_dir = path.parent().unwrap().to_path_buf();
+        std::fs::create_dir_all(&run_dir).unwrap();
+        std::fs::write(run_dir.join(LEGACY_SWEEP_MARKER), "").unwrap();
+
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let started = process_start_time(child.id()).unwrap();
+        let orphan = PidFile { pid: child.id(), started, owner_pid: u32::MAX - 2, owner_started: 1 };
+        write_to(&path, &orphan).unwrap();
+
+        let leftovers = find_stale_processes(&run_dir);
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert_eq!(leftovers.len(), 1);
+        assert_eq!(leftovers[0].0, "mariadb");
+        assert_eq!(leftovers[0].1, orphan);
+        assert_eq!(leftovers[0].2.as_deref(), Some(path.as_path()));
+    }
+
+    #[test]
+    fn the_legacy_sweep_runs_once() {
+        let path = pidfile_path("legacy");
+        let run_dir = path.parent().unwrap().to_path_buf();
+        assert!(!run_dir.join(LEGACY_SWEEP_MARKER).exists());
+        find_legacy_processes(&run_dir, &[]);
+        assert!(run_dir.join(LEGACY_SWEEP_MARKER).exists());
+        assert!(find_legacy_processes(&run_dir, &[]).is_empty());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn recognises_a_process_by_its_start_time() {
+        let pid = std::process::id();
+        let started = process_start_time(pid).unwrap();
+        assert!(is_same_process(pid, started));
+        // Same PID, different start time: the PID was reused by another process
+        assert!(!is_same_process(pid, started + 1));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn an_exited_process_is_not_the_same() {
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let started = process_start_time(child.id()).unwrap();
+        assert!(is_same_process(child.id(), started));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(!is_same_process(child.id(), started));
+    }
+}
@@ -0,0 +1,167 @@
+// Port Selection Module
+// Probes the configured ports before launch and falls back to free ones
+
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::config::RunnerConfig;
+
+/// How far above the configured port to look before asking the OS for any free port
+const SEARCH_RANGE: u16 = 100;
+
+/// Check that nothing else is listening on a local port
+pub fn is_port_available(port: u16) -> bool {
+    // Spring Boot binds all interfaces, so the wildcard address must be free as well
+    if TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).is_err() {
+        return false;
+    }
+    if TcpListener::bind((Ipv4Addr::LOCALHOST, port)).is_err() {
+        return false;
+    }
+
+    // "localhost" may resolve to ::1, where a different server could be answering
+    let v6 = SocketAddr::from((Ipv6Addr::LOCALHOST, port));
+    TcpStream::connect_timeout(&v6, Duration::from_millis(200)).is_err()
+}
+
+/// Find a free port, preferring `preferred` and the ports right above it
+fn find_free_port(preferred: u16, taken: &[u16]) -> Option<u16> {
+    let candidates = (0..=SEARCH_RANGE).filter_map(|offset| preferred.checked_add(offset));
+    for port in candidates {
+        if !taken.contains(&port) && is_port_available(port) {
+            return Some(port);
+        }
+    }
+
+    // Let the OS pick an ephemeral port as a last resort
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).ok()?;
+    let port = listener.local_addr().ok()?.port();
+    drop(listener);
+    (!taken.contains(&port) && is_port_available(port)).then_some(port)
+}
+
+/// Replace every configured port that is in use with a free one.
+/// Ports of services named in `running` are ours already and kept as they are.
+/// Returns true if the config was changed and should be persisted.
+pub fn resolve_ports(config: &mut RunnerConfig, running: &[&str]) -> Result<bool, String> {
+    let mut changed = false;
+    let mut taken: Vec<u16> = Vec::new();
+    let socket_only = config.mariadb.socket_only;
+    let external = config.external_database.enabled;
+
+    let ports = [
+        ("mariadb", "MariaDB", &mut config.ports.mariadb),
+        ("backend", "Backend", &mut config.ports.backend),
+        ("frontend", "Frontend", &mut config.ports.frontend),
+    ];
+
+    for (service, name, port) in ports {
+        // A socket-only or external database never binds the local port
+        if service == "mariadb" && (socket_only || external) {
+            continue;
+        }
+        if running.contains(&service) || (!taken.contains(port) && is_port_available(*port)) {
+            taken.push(*port);
+            continue;
+        }
+
+        let free = find_free_port(*port, &taken)
+            .ok_or_else(|| format!("No free port found for {} (configured: {})", name, port))?;
+        warn!("{} port {} is in use, switching to {}", name, port, free);
+        *port = free;
+        taken.push(free);
+        changed = true;
+    }
+
+    if !changed {
+        info!(
+            "Ports available: frontend {}, backend {}, MariaDB {}",
+            config.ports.frontend,
+            config.ports.backend,
+            if external {
+                "external".to_string()
+            } else if socket_only {
+                "socket only".to_string()
+            } else {
+                config.ports.mariadb.to_string()
+            }
+        );
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A port the OS considers free right now
+    fn free_port() -> u16 {
+        TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap().local_addr().unwrap().port()
+    }
+
+    /// Defaults with every port moved to a free one, so the tests do not depend on the machine
+    fn config() -> RunnerConfig {
+        let mut config = RunnerConfig::default();
+        config.ports.frontend = free_port();
+        config.ports.backend = free_port();
+        config.ports.mariadb = free_port();
+        config
+    }
+
+    #[test]
+    fn keeps_free_ports() {
+        let mut config = config();
+        let ports = config.ports.clone();
+        assert!(!resolve_ports(&mut config, &[]).unwrap());
+        assert_eq!(config.ports, ports);
+    }
+
+    #[test]
+    fn falls_back_when_a_port_is_in_use() {
+        let busy = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let busy_port = busy.local_addr().unwrap().port();
+        assert!(!is_port_available(busy_port));
+
+        let mut config = config();
+        config.ports.backend = busy_port;
+        assert!(resolve_ports(&mut config, &[]).unwrap());
+        assert_ne!(config.ports.backend, busy_port);
+        assert_ne!(config.ports.backend, config.ports.frontend);
+        assert_ne!(config.ports.backend, config.ports.mariadb);
+    }
+
+    #[test]
+    fn keeps_the_port_of_a_running_service() {
+        let busy = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let mut config = config();
+        config.ports.frontend = busy.local_addr().unwrap().port();
+        assert!(!resolve_ports(&mut config, &["frontend"]).unwrap());
+    }
+
+    #[test]
+    fn does_not_hand_out_the_same_port_twice() {
+        let mut config = config();
+        config.ports.frontend = config.ports.backend;
+        assert!(resolve_ports(&mut config, &[]).unwrap());
+        assert_ne!(config.ports.frontend, config.ports.backend);
+    }
+
+    #[test]
+    fn skips_the_mariadb_port_without_an_embedded_tcp_server() {
+        let busy = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let busy_port = busy.local_addr().unwrap().port();
+
+        let mut socket_only = config();
+        socket_only.ports.mariadb = busy_port;
+        socket_only.mariadb.socket_only = true;
+        assert!(!resolve_ports(&mut socket_only, &[]).unwrap());
+        assert_eq!(socket_only.ports.mariadb, busy_port);
+
+        let mut external = config();
+        external.ports.mariadb = busy_port;
+        external.external_database.enabled = true;
+        assert!(!resolve_ports(&mut external, &[]).unwrap());
+        assert_eq!(external.ports.mariadb, busy_port);
+    }
+}
@@ -0,0 +1,174 @@
+// Child Process Lifetime Module
+// Ties mariadbd and the JVM to the runner so they exit when it dies, even on a hard crash
+
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use tracing::warn;
+
+/// Put a child into its own process group and have it terminated when the runner dies.
+/// Must be applied before the command is spawned.
+///
+/// On Linux the kernel signals the child when the *thread* that spawned it exits, not the
+/// process. The command must therefore be spawned from an async task (runtime worker threads
+/// live as long as the runner), never from `spawn_blocking` or a short-lived thread, whose
+/// exit would terminate the child right away.
+pub fn bind_to_runner(command: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+
+        // Own group: terminal signals meant for the runner do not hit the child,
+        // and the whole group can be signalled at once
+        command.process_group(0);
+
+        #[cfg(target_os = "linux")]
+        {
+            let runner_pid = std::process::id();
+            // Tied to the spawning thread; see the constraint above
+            unsafe {
+                command.pre_exec(move || {
+                    if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    // The runner may have died between fork and prctl
+                    if libc::getppid() as u32 != runner_pid {
+                        libc::raise(libc::SIGTERM);
+                    }
+                    Ok(())
+                });
+            }
+        }
+    }
+}
+
+/// Watcher process holding the read end of a pipe only the runner can write to.
+/// When the runner exits for any reason the pipe closes and the watcher terminates the child's group.
+/// Used where the kernel cannot do this itself (PR_SET_PDEATHSIG is Linux only).
+pub struct DeathWatch {
+    pipe: Option<std::io::PipeWriter>,
+    watcher: Option<Child>,
+}
+
+impl DeathWatch {
+    /// Start watching the process group led by `pid`. After SIGTERM the group gets
+    /// `grace` to exit, the same deadline a normal stop allows, before it is killed.
+    /// Returns None on Linux, where `bind_to_runner` already has the kernel signal the child.
+    pub fn arm(pid: u32, grace: std::time::Duration) -> Result<Option<Self>, String> {
+        if cfg!(target_os = "linux") {
+            return Ok(None);
+        }
+        Self::spawn_watcher(pid, grace).map(Some)
+    }
+
+    fn spawn_watcher(pid: u32, grace: std::time::Duration) -> Result<Self, String> {
+        let (reader, writer) = std::io::pipe()
+            .map_err(|e| format!("Failed to create watchdog pipe: {}", e))?;
+
+        // `read` fails on EOF, i.e. when the runner is gone; a newline means a normal stop
+        let mut command = Command::new("/bin/sh");
+        command
+            .arg("-c")
+            .arg("read line || { kill -TERM -\"$0\"; sleep \"$1\"; kill -KILL -\"$0\"; } 2>/dev/null")
+            .arg(pid.to_string())
+            .arg(grace.as_secs().max(1).to_string())
+            .stdin(Stdio::from(reader))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            // Survive signals sent to the runner's process group
+            command.process_group(0);
+        }
+
+        let watcher = command.spawn()
+            .map_err(|e| format!("Failed to start watchdog: {}", e))?;
+        Ok(Self { pipe: Some(writer), watcher: Some(watcher) })
+    }
+
+    /// Release the watcher without touching the child, after it was stopped normally.
+    /// Does not block: the watcher exits on its own and is reaped in the background.
+    pub fn disarm(self) {
+        drop(self);
+    }
+}
+
+impl Drop for DeathWatch {
+    fn drop(&mut self) {
+        let Some(mut watcher) = self.watcher.take() else {
+            return;
+        };
+        if let Some(mut pipe) = self.pipe.take() {
+            if let Err(e) = pipe.write_all(b"\n") {
+                warn!("Failed to release watchdog: {}", e);
+                let _ = watcher.kill();
+            }
+        }
+        std::thread::spawn(move || {
+            let _ = watcher.wait();
+        });
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn sleeper() -> Child {
+        let mut command = Command::new("sleep");
+        command.arg("30");
+        bind_to_runner(&mut command);
+        command.spawn().unwrap()
+    }
+
+    /// Whether the child exits within `timeout`
+    fn exits_within(child: &mut Child, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if child.try_wait().unwrap().is_some() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        false
+    }
+
+    #[test]
+    fn a_bound_child_leads_its_own_group() {
+        let mut child = sleeper();
+        assert_eq!(unsafe { libc::getpgid(child.id() as i32) }, child.id() as i32);
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn the_kernel_signal_follows_the_spawning_thread() {
+        // Why bind_to_runner must not be used from a short-lived thread
+        let mut child = std::thread::spawn(sleeper).join().unwrap();
+        assert!(exits_within(&mut child, Duration::from_secs(5)));
+        assert!(DeathWatch::arm(child.id(), Duration::from_secs(1)).unwrap().is_none());
+    }
+
+    #[test]
+    fn the_watcher_stops_the_child_when_the_runner_goes_away() {
+        let mut child = sleeper();
+        let mut watch = DeathWatch::spawn_watcher(child.id(), Duration::from_secs(1)).unwrap();
+        assert!(!exits_within(&mut child, Duration::from_millis(300)));
+
+        // Closing the pipe without the release newline is what the runner's death looks like
+        drop(watch.pipe.take());
+        assert!(exits_within(&mut child, Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn a_disarmed_watcher_leaves_the_child_alone() {
+        let mut child = sleeper();
+        let watch = DeathWatch::spawn_watcher(child.id(), Duration::from_secs(1)).unwrap();
+        watch.disarm();
+        assert!(!exits_within(&mut child, Duration::from_millis(1500)));
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
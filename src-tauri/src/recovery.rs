@@ -0,0 +1,108 @@
+// Crash Recovery Module
+// Detects an unclean MariaDB shutdown and checks the library tables before the backend uses them
+
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tracing::{error, info, warn};
+
+use crate::credentials::DbCredentials;
+use crate::mariadb::DbAddress;
+
+/// Written into the data directory when MariaDB exits cleanly, removed once it starts again
+const CLEAN_SHUTDOWN_MARKER: &str = "booklore_clean_shutdown";
+
+/// Tables found damaged by the last check; the backend is not started while any are listed
+#[derive(Default)]
+pub struct DamagedTables {
+    tables: Mutex<Vec<String>>,
+}
+
+impl DamagedTables {
+    /// Tables the last check or repair left damaged
+    pub fn list(&self) -> Vec<String> {
+        self.tables.lock().map(|tables| tables.clone()).unwrap_or_default()
+    }
+
+    /// Forget damaged tables, e.g. after the schema was replaced by a backup
+    pub fn clear(&self) {
+        self.set(Vec::new());
+    }
+
+    fn set(&self, tables: Vec<String>) {
+        if let Ok(mut damaged) = self.tables.lock() {
+            *damaged = tables;
+        }
+    }
+}
+
+/// Whether the last run of the server in `data_dir` ended with a clean shutdown
+pub fn had_clean_shutdown(data_dir: &Path) -> bool {
+    data_dir.join(CLEAN_SHUTDOWN_MARKER).exists()
+}
+
+/// Record that the server in `data_dir` has stopped cleanly
+pub fn mark_clean_shutdown(data_dir: &Path) {
+    if let Err(e) = std::fs::write(data_dir.join(CLEAN_SHUTDOWN_MARKER), b"") {
+        warn!("Failed to write clean shutdown marker: {}", e);
+    }
+}
+
+/// Forget the clean shutdown of the previous run; the server is running again
+pub fn clear_clean_shutdown(data_dir: &Path) {
+    let _ = std::fs::remove_file(data_dir.join(CLEAN_SHUTDOWN_MARKER));
+}
+
+/// Check every booklore table after an unclean shutdown, reporting progress to the UI
+pub async fn check_after_crash(app: &AppHandle, address: &DbAddress, credentials: &DbCredentials) -> Result<(), String> {
+    info!("Checking database tables after an unclean shutdown");
+    crate::tray::update_status(app, "Checking database...");
+    crate::emit_status(app, "recovery", "active", "Checking tables after an unclean shutdown...", 25);
+
+    let damaged = crate::mariadb::check_tables(address, credentials, |done, total, table| {
+        let message = format!("Checking table {} ({}/{})", table, done, total);
+        crate::emit_status(app, "recovery", "active", &message, 25);
+    }).await?;
+
+    if damaged.is_empty() {
+        info!("All database tables are intact");
+        crate::emit_status(app, "recovery", "complete", "Database tables are intact", 28);
+    } else {
+        error!("Damaged database tables: {}", damaged.join(", "));
+        crate::tray::update_status(app, "Database needs repair");
+    }
+    app.state::<crate::AppState>().damaged_tables.set(damaged);
+    Ok(())
+}
+
+/// Repair the tables found damaged; fails if any of them is still damaged afterwards
+pub async fn repair(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<crate::AppState>();
+    let tables = state.damaged_tables.list();
+    if tables.is_empty() {
+        return Ok(());
+    }
+
+    let config = state.settings().await?;
+    let credentials = crate::credentials::load()?
+        .filter(|c| c.provisioned)
+        .ok_or("Database credentials have not been set up yet")?;
+    let address = DbAddress::from_config(&config);
+
+    crate::emit_status(app, "recovery", "active", &format!("Repairing {} table(s)...", tables.len()), 25);
+    let still_damaged = crate::mariadb::repair_tables(&address, &credentials, &tables).await?;
+    state.damaged_tables.set(still_damaged.clone());
+
+    if still_damaged.is_empty() {
+        info!("Repaired tables: {}", tables.join(", "));
+        crate::emit_status(app, "recovery", "complete", "Database tables repaired", 28);
+        Ok(())
+    } else {
+        let message = format!(
+            "Could not repair: {}. Restore a backup to continue.",
+            still_damaged.join(", ")
+        );
+        crate::emit_status(app, "recovery", "error", &message, 28);
+        Err(message)
+    }
+}
@@ -0,0 +1,849 @@
+// Service Supervisor Module
+// Owns every managed process (MariaDB, backend, frontend) and drives their lifecycle
+
+use std::collections::{BTreeMap, HashMap};
+use std::process::Child;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{watch, Mutex, Notify};
+use tracing::{info, warn, error};
+
+/// Lifecycle state of a managed service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceState {
+    Starting,
+    Healthy,
+    Degraded,
+    Stopping,
+    Stopped,
+    Failed,
+}
+
+/// How a stop request ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopOutcome {
+    /// Exited on its own before the deadline
+    Clean,
+    /// Exited before the deadline, but with an error status or from a crash,
+    /// or could no longer be observed
+    Failed,
+    /// Still running at the deadline and killed
+    TimeoutKilled,
+    /// Had already exited (or was not running) when the stop was requested
+    AlreadyDead,
+}
+
+impl std::fmt::Display for StopOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Clean => write!(f, "stopped cleanly"),
+            Self::Failed => write!(f, "did not exit cleanly"),
+            Self::TimeoutKilled => write!(f, "killed after the stop deadline"),
+            Self::AlreadyDead => write!(f, "had already exited"),
+        }
+    }
+}
+
+/// How often a stopping process is polled for exit
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What a running service is backed by
+pub enum ServiceHandle {
+    /// External child process (mariadbd, java)
+    Process(Child),
+    /// In-process task (axum frontend server)
+    Task(tokio::task::JoinHandle<()>),
+}
+
+/// How the watchdog reacts when a service dies or stops answering health checks
+#[derive(Debug, Clone, Copy)]
+pub struct RestartPolicy {
+    /// Restarts allowed within `window` before the service is marked failed
+    pub max_restarts: u32,
+    /// Sliding window for counting restarts
+    pub window: Duration,
+    /// Delay before the first restart, doubled on every further attempt
+    pub initial_backoff: Duration,
+    /// Upper bound for the restart delay
+    pub max_backoff: Duration,
+    /// How often the watchdog checks the process and its health
+    pub check_interval: Duration,
+    /// Consecutive failed health checks before a running process is restarted
+    pub unhealthy_threshold: u32,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            window: Duration::from_secs(600),
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(60),
+            check_interval: Duration::from_secs(10),
+            unhealthy_threshold: 3,
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Delay before the given restart attempt (1-based)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// A process managed by the supervisor
+#[async_trait]
+pub trait Service: Send + Sync {
+    /// Service identifier, also used as the event stage ("mariadb", "backend", "frontend")
+    fn name(&self) -> &'static str;
+
+    /// Human readable name for status messages
+    fn label(&self) -> &'static str {
+        self.name()
+    }
+
+    /// Launch the service and hand back its handle
+    async fn spawn(&self, app: &AppHandle) -> Result<ServiceHandle, String>;
+
+    /// Wait until the service accepts requests
+    async fn wait_ready(&self, _app: &AppHandle) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Ask the service to shut down on its own terms.
+    /// Returns false if the supervisor should send SIGTERM instead.
+    async fn request_shutdown(&self) -> bool {
+        false
+    }
+
+    /// Deadline for the process to exit after the stop request before it is killed
+    fn stop_timeout(&self) -> Duration {
+        Duration::from_secs(10)
+    }
+
+    /// Clean up leftovers (sockets, temp files) after the service stopped
+    async fn cleanup(&self, _outcome: StopOutcome) {}
+
+    /// Liveness probe used by the watchdog while the service is running
+    async fn health_check(&self) -> bool {
+        true
+    }
+
+    /// Restart behaviour on crash; None disables the watchdog
+    fn restart_policy(&self) -> Option<RestartPolicy> {
+        None
+    }
+}
+
+/// Status payload sent to the UI whenever a service changes state
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ServiceStatus {
+    pub service: String,
+    pub state: ServiceState,
+    pub message: String,
+}
+
+/// Bookkeeping for one managed service
+struct Entry {
+    service: Option<Arc<dyn Service>>,
+    handle: Option<ServiceHandle>,
+    state: ServiceState,
+    /// Bumped on every start/stop so stale watchdogs know to exit
+    generation: u64,
+    /// When the watchdog restarted the service, for crash-loop detection
+    restarts: Vec<Instant>,
+    /// Terminates the process if the runner dies without stopping it
+    death_watch: Option<crate::process::DeathWatch>,
+    /// Signalled by stop() to abandon a start that is still waiting for readiness
+    cancel: Arc<Notify>,
+    /// Result of the start in progress, for callers that arrive while it is still running
+    starting: Option<watch::Receiver<Option<Result<(), String>>>>,
+    /// Outcome of the stop in progress, for callers that arrive while it is still running
+    stopping: Option<watch::Receiver<Option<StopOutcome>>>,
+}
+
+impl Entry {
+    fn new() -> Self {
+        Self {
+            service: None,
+            handle: None,
+            state: ServiceState::Stopped,
+            generation: 0,
+            restarts: Vec::new(),
+            death_watch: None,
+            cancel: Arc::new(Notify::new()),
+            starting: None,
+            stopping: None,
+        }
+    }
+
+    /// Move to `state` unless a start or stop since `generation` made the caller stale.
+    /// Returns whether the state was changed.
+    fn transition(&mut self, generation: u64, state: ServiceState) -> bool {
+        if self.generation != generation {
+            return false;
+        }
+        self.state = state;
+        true
+    }
+}
+
+/// Owns the handles of every managed service
+pub struct Supervisor {
+    entries: Mutex<HashMap<&'static str, Arc<Mutex<Entry>>>>,
+    /// Latest state of every service that was ever started
+    status: std::sync::Mutex<BTreeMap<String, ServiceStatus>>,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            status: std::sync::Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current state of every known service
+    pub fn snapshot(&self) -> Vec<ServiceStatus> {
+        self.status.lock()
+            .map(|status| status.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Get (or create) the entry for a service
+    async fn entry(&self, name: &'static str) -> Arc<Mutex<Entry>> {
+        let mut entries = self.entries.lock().await;
+        entries
+            .entry(name)
+            .or_insert_with(|| Arc::new(Mutex::new(Entry::new())))
+            .clone()
+    }
+
+    /// Look up an existing entry by name
+    async fn find(&self, name: &str) -> Option<Arc<Mutex<Entry>>> {
+        self.entries.lock().await.get(name).cloned()
+    }
+
+    /// Start a service and wait for it to become ready
+    pub async fn start(self: &Arc<Self>, app: &AppHandle, service: Arc<dyn Service>) -> Result<(), String> {
+        let name = service.name();
+
+        // A failed service may still hold a process that never became ready
+        if self.state(name).await == ServiceState::Failed {
+            self.stop(app, name).await?;
+        }
+
+        let entry = self.entry(name).await;
+
+        // Claim the service; preparing it (downloads, installs, upgrades) happens without the
+        // entry lock, so stop() and status queries are not held up for minutes
+        let (generation, done) = loop {
+            let mut guard = entry.lock().await;
+            if let Some(stopping) = pending(&guard.stopping) {
+                // Let the previous process exit before launching a new one
+                drop(guard);
+                info!("{} is still stopping, waiting before starting it", name);
+                shared_result(stopping).await;
+                continue;
+            }
+            if let Some(starting) = pending(&guard.starting) {
+                drop(guard);
+                info!("{} is already starting, waiting for it", name);
+                return shared_result(starting).await
+                    .unwrap_or_else(|| Err(format!("{} did not finish starting", service.label())));
+            }
+            if guard.handle.is_some() {
+                info!("{} already running", name);
+                return Ok(());
+            }
+
+            guard.generation += 1;
+            guard.service = Some(service.clone());
+            guard.state = ServiceState::Starting;
+            guard.cancel = Arc::new(Notify::new());
+            let (done, starting) = watch::channel(None);
+            guard.starting = Some(starting);
+            self.publish(app, name, ServiceState::Starting, "Starting");
+            break (guard.generation, done);
+        };
+
+        let result = self.launch(app, &entry, service, generation).await;
+
+        {
+            let mut guard = entry.lock().await;
+            // A stop() meanwhile already released the claim
+            if guard.generation == generation {
+                guard.starting = None;
+            }
+        }
+        done.send_replace(Some(result.clone()));
+        result
+    }
+
+    /// Spawn a claimed service, wait for readiness and hand it to the watchdog
+    async fn launch(self: &Arc<Self>, app: &AppHandle, entry: &Mutex<Entry>, service: Arc<dyn Service>, generation: u64) -> Result<(), String> {
+        let name = service.name();
+
+        let handle = match service.spawn(app).await {
+            Ok(handle) => handle,
+            Err(e) => {
+                self.set_state_if(app, name, generation, ServiceState::Failed, &e).await;
+                return Err(e);
+            }
+        };
+
+        let cancel = {
+            let mut guard = entry.lock().await;
+            if guard.generation != generation {
+                // stop() was called while the service was being prepared
+                drop(guard);
+                discard(handle, service.as_ref()).await;
+                return Err(format!("{} was stopped while starting", service.label()));
+            }
+
+            if let ServiceHandle::Process(child) = &handle {
+                info!("{} started with PID: {}", name, child.id());
+                crate::pidfile::write(name, child.id());
+                match crate::process::DeathWatch::arm(child.id(), service.stop_timeout()) {
+                    Ok(watch) => guard.death_watch = watch,
+                    Err(e) => warn!("{} will not be stopped if the runner crashes: {}", name, e),
+                }
+            }
+            guard.handle = Some(handle);
+            guard.cancel.clone()
+        };
+
+        // Readiness is awaited without holding the entry lock; stop() cuts the wait short
+        let ready = tokio::select! {
+            result = service.wait_ready(app) => result,
+            _ = cancel.notified() => Err(format!("{} was stopped while starting", service.label())),
+        };
+        match ready {
+            Ok(()) => {
+                info!("{} is ready", name);
+                let current = self.set_state_if(app, name, generation, ServiceState::Healthy, "Ready").await;
+
+                if let (true, Some(policy)) = (current, service.restart_policy()) {
+                    self.spawn_watchdog(app, service, policy, generation);
+                }
+                Ok(())
+            }
+            Err(e) => {
+                self.set_state_if(app, name, generation, ServiceState::Failed, &e).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Stop a service: graceful request, wait for exit until the deadline, then kill
+    pub async fn stop(&self, app: &AppHandle, name: &str) -> Result<StopOutcome, String> {
+        let Some(entry) = self.find(name).await else {
+            return Ok(StopOutcome::AlreadyDead);
+        };
+
+        // The handle is taken under the lock, but the exit is awaited without it,
+        // so status queries and other services are not held up until the deadline
+        let (handle, service, death_watch, generation, done) = {
+            let mut guard = entry.lock().await;
+            if let Some(stopping) = pending(&guard.stopping) {
+                drop(guard);
+                info!("{} is already stopping, waiting for it", name);
+                return Ok(shared_result(stopping).await.unwrap_or(StopOutcome::Failed));
+            }
+
+            // Invalidate the watchdog, including one waiting to restart the service,
+            // and any start still waiting for readiness
+            guard.generation += 1;
+            guard.cancel.notify_one();
+            guard.starting = None;
+            let Some(handle) = guard.handle.take() else {
+                if guard.state != ServiceState::Stopped {
+                    guard.state = ServiceState::Stopped;
+                    self.publish(app, name, ServiceState::Stopped, "Stopped");
+                }
+                return Ok(StopOutcome::AlreadyDead);
+            };
+
+            info!("Stopping {}...", name);
+            guard.state = ServiceState::Stopping;
+            self.publish(app, name, ServiceState::Stopping, "Stopping");
+            let (done, stopping) = watch::channel(None);
+            guard.stopping = Some(stopping);
+            (handle, guard.service.clone(), guard.death_watch.take(), guard.generation, done)
+        };
+
+        let outcome = match handle {
+            ServiceHandle::Process(child) => {
+                let timeout = service.as_ref()
+                    .map(|s| s.stop_timeout())
+                    .unwrap_or(Duration::from_secs(10));
+                let outcome = stop_process(child, service.as_deref(), timeout).await;
+
+                crate::pidfile::remove(name);
+                if let Some(watch) = death_watch {
+                    watch.disarm();
+                }
+                outcome
+            }
+            ServiceHandle::Task(task) => {
+                task.abort();
+                StopOutcome::Clean
+            }
+        };
+
+        if let Some(service) = &service {
+            service.cleanup(outcome).await;
+        }
+
+        {
+            let mut guard = entry.lock().await;
+            guard.stopping = None;
+            if guard.generation == generation {
+                guard.state = ServiceState::Stopped;
+                self.publish(app, name, ServiceState::Stopped, "Stopped");
+            }
+        }
+        done.send_replace(Some(outcome));
+        match outcome {
+            StopOutcome::TimeoutKilled | StopOutcome::Failed => warn!("{} {}", name, outcome),
+            _ => info!("{} {}", name, outcome),
+        }
+        Ok(outcome)
+    }
+
+    /// Current state of a service
+    pub async fn state(&self, name: &str) -> ServiceState {
+        match self.find(name).await {
+            Some(entry) => entry.lock().await.state,
+            None => ServiceState::Stopped,
+        }
+    }
+
+    /// Whether the supervisor holds a live handle for a service
+    pub async fn is_running(&self, name: &str) -> bool {
+        match self.find(name).await {
+            Some(entry) => entry.lock().await.handle.is_some(),
+            None => false,
+        }
+    }
+
+    /// Watch a running service until it is stopped or restarted
+    fn spawn_watchdog(self: &Arc<Self>, app: &AppHandle, service: Arc<dyn Service>, policy: RestartPolicy, generation: u64) {
+        let supervisor = self.clone();
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            supervisor.watch(&app, service, policy, generation).await;
+        });
+    }
+
+    async fn watch(self: &Arc<Self>, app: &AppHandle, service: Arc<dyn Service>, policy: RestartPolicy, generation: u64) {
+        let name = service.name();
+        let entry = self.entry(name).await;
+        let mut failures = 0u32;
+
+        loop {
+            tokio::time::sleep(policy.check_interval).await;
+
+            // Has the process exited?
+            let exited = {
+                let mut guard = entry.lock().await;
+                if guard.generation != generation {
+                    return;
+                }
+                match guard.handle.as_mut() {
+                    Some(ServiceHandle::Process(child)) => match child.try_wait() {
+                        Ok(Some(status)) => Some(format!("process exited ({})", status)),
+                        Ok(None) => None,
+                        Err(e) => Some(format!("process lost ({})", e)),
+                    },
+                    Some(ServiceHandle::Task(task)) => task.is_finished().then(|| "task ended".to_string()),
+                    None => return,
+                }
+            };
+
+            let reason = match exited {
+                Some(reason) => reason,
+                None if service.health_check().await => {
+                    if failures > 0 {
+                        failures = 0;
+                        info!("{} health check recovered", name);
+                        self.set_state_if(app, name, generation, ServiceState::Healthy, "Recovered").await;
+                        crate::tray::update_status(app, &format!("{} running", service.label()));
+                    }
+                    continue;
+                }
+                None => {
+                    failures += 1;
+                    warn!("{} health check failed ({}/{})", name, failures, policy.unhealthy_threshold);
+                    if failures < policy.unhealthy_threshold {
+                        let message = format!("Health check failed ({}/{})", failures, policy.unhealthy_threshold);
+                        self.set_state_if(app, name, generation, ServiceState::Degraded, &message).await;
+                        crate::tray::update_status(app, &format!("{} not responding", service.label()));
+                        continue;
+                    }
+                    format!("{} consecutive health checks failed", failures)
+                }
+            };
+
+            warn!("{} needs a restart: {}", name, reason);
+            self.restart(app, service, policy, generation, reason).await;
+            return;
+        }
+    }
+
+    /// Restart a crashed or hung service with exponential backoff
+    async fn restart(self: &Arc<Self>, app: &AppHandle, service: Arc<dyn Service>, policy: RestartPolicy, mut generation: u64, mut reason: String) {
+        let name = service.name();
+        let label = service.label();
+        let entry = self.entry(name).await;
+
+        loop {
+            let (handle, death_watch) = {
+                let mut guard = entry.lock().await;
+                if guard.generation != generation {
+                    return;
+                }
+                (guard.handle.take(), guard.death_watch.take())
+            };
+
+            // Reap the dead (or hung) process without holding the entry lock
+            match handle {
+                Some(ServiceHandle::Process(child)) => {
+                    kill_and_reap(child).await;
+                    crate::pidfile::remove(name);
+                }
+                Some(ServiceHandle::Task(task)) => task.abort(),
+                None => {}
+            }
+            if let Some(watch) = death_watch {
+                watch.disarm();
+            }
+
+            let attempt = {
+                let mut guard = entry.lock().await;
+                if guard.generation != generation {
+                    return;
+                }
+
+                let now = Instant::now();
+                guard.restarts.retain(|t| now.duration_since(*t) < policy.window);
+                if guard.restarts.len() as u32 >= policy.max_restarts {
+                    let message = format!(
+                        "{} keeps crashing ({} restarts in {} minutes), giving up: {}",
+                        label, guard.restarts.len(), policy.window.as_secs() / 60, reason
+                    );
+                    error!("{}", message);
+                    guard.state = ServiceState::Failed;
+                    self.publish(app, name, ServiceState::Failed, &message);
+                    crate::emit_status(app, name, "error", &message, 100);
+                    crate::tray::update_status(app, &format!("{} failed", label));
+                    return;
+                }
+
+                guard.restarts.push(now);
+                guard.state = ServiceState::Degraded;
+                guard.restarts.len() as u32
+            };
+
+            let delay = policy.backoff(attempt);
+            let message = format!(
+                "{} stopped unexpectedly ({}). Restarting in {}s (attempt {}/{})...",
+                label, reason, delay.as_secs(), attempt, policy.max_restarts
+            );
+            warn!("{}", message);
+            self.publish(app, name, ServiceState::Degraded, &message);
+            crate::emit_status(app, name, "active", &message, 70);
+            crate::tray::update_status(app, &format!("{} restarting", label));
+
+            tokio::time::sleep(delay).await;
+
+            // Give up if the service was stopped or started by someone else meanwhile
+            if entry.lock().await.generation != generation {
+                return;
+            }
+
+            match self.start(app, service.clone()).await {
+                Ok(()) => {
+                    info!("{} restarted after {} attempt(s)", name, attempt);
+                    crate::emit_status(app, name, "complete", &format!("{} restarted", label), 100);
+                    crate::tray::update_status(app, &format!("{} running", label));
+                    return;
+                }
+                Err(e) => {
+                    reason = e;
+                    generation = entry.lock().await.generation;
+                }
+            }
+        }
+    }
+
+    /// Record a service state change and send it to the UI
+    fn publish(&self, app: &AppHandle, service: &str, state: ServiceState, message: &str) {
+        let payload = ServiceStatus {
+            service: service.to_string(),
+            state,
+            message: message.to_string(),
+        };
+        if let Ok(mut status) = self.status.lock() {
+            status.insert(service.to_string(), payload.clone());
+        }
+
+        if let Err(e) = app.emit("service-status", payload) {
+            error!("Failed to emit service status: {}", e);
+        }
+    }
+
+    /// Update the state unless the service was restarted or stopped in the meantime.
+    /// Returns whether the update was applied.
+    async fn set_state_if(&self, app: &AppHandle, name: &'static str, generation: u64, state: ServiceState, message: &str) -> bool {
+        let entry = self.entry(name).await;
+        let mut guard = entry.lock().await;
+        if !guard.transition(generation, state) {
+            return false;
+        }
+        self.publish(app, name, state, message);
+        true
+    }
+}
+
+/// The receiver of an operation still in progress; a closed channel means its caller was dropped
+fn pending<T>(rx: &Option<watch::Receiver<Option<T>>>) -> Option<watch::Receiver<Option<T>>> {
+    rx.as_ref().filter(|rx| rx.has_changed().is_ok()).cloned()
+}
+
+/// Wait for the result another caller publishes; None if it gave up without one
+async fn shared_result<T: Clone>(mut rx: watch::Receiver<Option<T>>) -> Option<T> {
+    rx.wait_for(|value| value.is_some()).await.ok().and_then(|value| value.clone())
+}
+
+/// Stop a handle that was spawned after its start had already been called off
+async fn discard(handle: ServiceHandle, service: &dyn Service) {
+    match handle {
+        ServiceHandle::Process(child) => {
+            let outcome = stop_process(child, Some(service), service.stop_timeout()).await;
+            service.cleanup(outcome).await;
+        }
+        ServiceHandle::Task(task) => task.abort(),
+    }
+}
+
+/// Ask a process to exit and poll until it does, killing it once `timeout` has passed
+async fn stop_process(mut child: Child, service: Option<&dyn Service>, timeout: Duration) -> StopOutcome {
+    if let Ok(Some(status)) = child.try_wait() {
+        info!("Process {} had already exited ({})", child.id(), status);
+        return StopOutcome::AlreadyDead;
+    }
+
+    let handled = match service {
+        Some(service) => service.request_shutdown().await,
+        None => false,
+    };
+
+    // Send SIGTERM unless the service asked to shut down itself
+    #[cfg(unix)]
+    if !handled {
+        signal_group(&child, libc::SIGTERM);
+    }
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if exited_cleanly(&status, handled) => return StopOutcome::Clean,
+            Ok(Some(status)) => {
+                warn!("Process {} exited with {} while stopping", child.id(), status);
+                return StopOutcome::Failed;
+            }
+            Ok(None) if Instant::now() < deadline => tokio::time::sleep(STOP_POLL_INTERVAL).await,
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Lost track of process {} while stopping it: {}", child.id(), e);
+                kill_and_reap(child).await;
+                return StopOutcome::Failed;
+            }
+        }
+    }
+
+    warn!("Process {} did not exit within {}s, killing it", child.id(), timeout.as_secs());
+    kill_and_reap(child).await;
+    StopOutcome::TimeoutKilled
+}
+
+/// SIGKILL the process and everything in its process group, then reap it off the async runtime
+async fn kill_and_reap(mut child: Child) {
+    #[cfg(unix)]
+    signal_group(&child, libc::SIGKILL);
+    let _ = child.kill();
+    let _ = tokio::task::spawn_blocking(move || child.wait()).await;
+}
+
+/// Signal the process and everything in its process group.
+/// Managed processes lead their own group, so helpers they started get the signal as well.
+#[cfg(unix)]
+fn signal_group(child: &Child, signal: i32) {
+    let pid = child.id() as i32;
+    let target = if unsafe { libc::getpgid(pid) } == pid { -pid } else { pid };
+    unsafe {
+        libc::kill(target, signal);
+    }
+}
+
+/// A zero exit status, or dying from the SIGTERM the supervisor sent (the JVM exits with 143 then)
+fn exited_cleanly(status: &std::process::ExitStatus, handled: bool) -> bool {
+    if status.success() {
+        return true;
+    }
+    #[cfg(unix)]
+    if !handled {
+        use std::os::unix::process::ExitStatusExt;
+        return status.signal() == Some(libc::SIGTERM) || status.code() == Some(128 + libc::SIGTERM);
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Command, Stdio};
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RestartPolicy::default();
+        let delays: Vec<u64> = (1..=7).map(|attempt| policy.backoff(attempt).as_secs()).collect();
+        assert_eq!(delays, [2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(policy.backoff(0), policy.initial_backoff);
+        // Far beyond u32 range for the factor, still capped
+        assert_eq!(policy.backoff(u32::MAX), policy.max_backoff);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sigterm_counts_as_clean_only_if_the_supervisor_sent_it() {
+        use std::os::unix::process::ExitStatusExt;
+        let exited = |code: i32| std::process::ExitStatus::from_raw(code << 8);
+        let signalled = |signal: i32| std::process::ExitStatus::from_raw(signal);
+
+        assert!(exited_cleanly(&exited(0), true));
+        assert!(exited_cleanly(&exited(0), false));
+        assert!(!exited_cleanly(&exited(1), false));
+
+        assert!(exited_cleanly(&signalled(libc::SIGTERM), false));
+        assert!(exited_cleanly(&exited(128 + libc::SIGTERM), false));
+        // The service shut down on its own, so a SIGTERM came from someone else
+        assert!(!exited_cleanly(&signalled(libc::SIGTERM), true));
+        assert!(!exited_cleanly(&signalled(libc::SIGKILL), false));
+    }
+
+    #[tokio::test]
+    async fn late_callers_share_the_result_in_progress() {
+        let (done, rx) = watch::channel(None);
+        let slot = Some(rx);
+        let waiting = pending(&slot).unwrap();
+
+        let waiter = tokio::spawn(shared_result(waiting));
+        done.send_replace(Some(Ok::<(), String>(())));
+        assert_eq!(waiter.await.unwrap(), Some(Ok(())));
+    }
+
+    #[tokio::test]
+    async fn an_abandoned_operation_is_not_pending() {
+        let (done, rx) = watch::channel::<Option<StopOutcome>>(None);
+        let slot = Some(rx);
+        let waiting = pending(&slot).unwrap();
+
+        // The caller that owned the operation was dropped before it published anything
+        drop(done);
+        assert!(pending(&slot).is_none());
+        assert_eq!(shared_result(waiting).await, None);
+        assert!(pending::<StopOutcome>(&None).is_none());
+    }
+
+    #[test]
+    fn a_stale_generation_cannot_change_the_state() {
+        let mut entry = Entry::new();
+        entry.generation += 1;
+        let watchdog = entry.generation;
+        assert!(entry.transition(watchdog, ServiceState::Healthy));
+
+        // stop() bumps the generation, so the old watchdog must not mark the service degraded
+        entry.generation += 1;
+        entry.state = ServiceState::Stopped;
+        assert!(!entry.transition(watchdog, ServiceState::Degraded));
+        assert_eq!(entry.state, ServiceState::Stopped);
+    }
+
+    /// Spawn a child in its own process group, as `bind_to_runner` does
+    #[cfg(unix)]
+    fn spawn(script: &str) -> Child {
+        use std::os::unix::process::CommandExt;
+        Command::new("/bin/sh")
+            .arg("-c")
+            .arg(script)
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap()
+    }
+
+    /// Block until the child reports that its signal handling is set up
+    #[cfg(unix)]
+    fn wait_until_ready(child: &mut Child) {
+        use std::io::BufRead;
+        let mut line = String::new();
+        std::io::BufReader::new(child.stdout.as_mut().unwrap()).read_line(&mut line).unwrap();
+        assert_eq!(line.trim(), "ready");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn a_process_that_honours_sigterm_stops_cleanly() {
+        let mut child = spawn("echo ready; exec sleep 30");
+        wait_until_ready(&mut child);
+        let outcome = stop_process(child, None, Duration::from_secs(5)).await;
+        assert_eq!(outcome, StopOutcome::Clean);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn a_process_that_ignores_sigterm_is_killed_at_the_deadline() {
+        let mut child = spawn("trap '' TERM; echo ready; sleep 30");
+        wait_until_ready(&mut child);
+        let outcome = stop_process(child, None, Duration::from_millis(300)).await;
+        assert_eq!(outcome, StopOutcome::TimeoutKilled);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn sigterm_reaches_the_whole_process_group() {
+        // The leader ignores SIGTERM but leaves once its helper is gone; the helper only
+        // stops if it gets the graceful signal too, long before the deadline
+        let mut child = spawn("sleep 30 & trap '' TERM; echo ready; wait $! 2>/dev/null; exit 0");
+        wait_until_ready(&mut child);
+        let started = Instant::now();
+        let outcome = stop_process(child, None, Duration::from_secs(10)).await;
+        assert_eq!(outcome, StopOutcome::Clean);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn a_process_that_already_exited_is_reported_as_such() {
+        let mut child = spawn("exit 0");
+        child.wait().unwrap();
+        let outcome = stop_process(child, None, Duration::from_secs(5)).await;
+        assert_eq!(outcome, StopOutcome::AlreadyDead);
+    }
+}
@@ -0,0 +1,147 @@
+// Shutdown Coordinator Module
+// The one ordered way out for tray Quit, menu Quit and OS exit: backend, then MariaDB, then frontend.
+// Restart and the stop command use the same order without exiting.
+
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{error, info, warn};
+
+use crate::service::StopOutcome;
+use crate::AppState;
+
+/// Dependents first, so nothing loses its database while still running
+const STOP_ORDER: [&str; 3] = ["backend", "mariadb", "frontend"];
+
+/// Begin the shutdown with progress in the main window, then exit. Repeated requests are ignored.
+pub fn request(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    if state.is_shutting_down.swap(true, Ordering::SeqCst) {
+        info!("Shutdown already in progress");
+        return;
+    }
+
+    info!("Initiating graceful shutdown sequence...");
+
+    // Show the window if it was hidden
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+
+        // Navigate to loader page to show progress with shutdown flag
+        #[cfg(debug_assertions)]
+        let url = "http://localhost:1420?shutdown=true";
+        #[cfg(not(debug_assertions))]
+        let url = "tauri://localhost?shutdown=true";
+
+        // We need to navigate back to the wrapper UI
+        let _ = window.eval(format!("window.location.href = '{}'", url));
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        // Give UI time to load
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+        stop_all(&app).await;
+
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        app.exit(0);
+    });
+}
+
+/// Stop every service in order, exactly once. Callers arriving while it runs wait for it to finish.
+pub async fn stop_all(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    state.stopped.get_or_init(|| stop_sequence(app)).await;
+}
+
+/// Stop every service in the shutdown order and keep the app running, as for a restart.
+/// Every service is asked to stop even if an earlier one fails; the first error is returned.
+pub async fn stop_services(app: &AppHandle) -> Result<(), String> {
+    info!("Stopping BookLore services...");
+    let mut result = Ok(());
+    for name in STOP_ORDER {
+        if let Err(e) = stop(app, name).await {
+            result = result.and(Err(e));
+        }
+    }
+    if result.is_ok() {
+        info!("All services stopped");
+    }
+    result
+}
+
+/// A restore swaps the data directory; let it finish (or roll back) before its services are stopped
+async fn wait_for_restore(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    if !state.restoring.load(Ordering::SeqCst) {
+        return;
+    }
+    info!("Waiting for the restore to finish before shutting down");
+    crate::emit_shutdown_status(app, "mariadb", "active", "Waiting for the restore to finish...", 10);
+    while state.restoring.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+async fn stop_sequence(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    state.is_shutting_down.store(true, Ordering::SeqCst);
+    state.events.clear();
+    let _ = app.emit("shutdown-start", ());
+
+    // Launches refuse to run from here on, so the restore ends instead of starting services again
+    wait_for_restore(app).await;
+
+    // 1. Stop Backend
+    crate::emit_shutdown_status(app, "backend", "active", "Stopping backend...", 20);
+    match stop(app, "backend").await {
+        Ok(outcome) => {
+            crate::emit_shutdown_status(app, "backend", "complete", &stopped_message("Backend", outcome), 50);
+        }
+        Err(e) => {
+            crate::emit_shutdown_status(app, "backend", "error", &format!("Error: {}", e), 50);
+        }
+    }
+
+    // 2. Stop MariaDB
+    crate::emit_shutdown_status(app, "mariadb", "active", "Stopping database...", 60);
+
+    // Mark JRE as skipped/done since we don't manage it explicitly during stop
+    crate::emit_shutdown_status(app, "jre", "complete", "Runtime stopped", 60);
+
+    match stop(app, "mariadb").await {
+        Ok(outcome) => {
+            crate::emit_shutdown_status(app, "mariadb", "complete", &stopped_message("Database", outcome), 90);
+        }
+        Err(e) => {
+            crate::emit_shutdown_status(app, "mariadb", "error", &format!("Error: {}", e), 90);
+        }
+    }
+
+    // 3. Stop Frontend (last)
+    let _ = stop(app, "frontend").await;
+
+    crate::emit_shutdown_status(app, "backend", "complete", "Goodnight!", 100);
+    info!("All services stopped");
+}
+
+async fn stop(app: &AppHandle, name: &str) -> Result<StopOutcome, String> {
+    let result = app.state::<AppState>().supervisor.stop(app, name).await;
+    match &result {
+        Ok(StopOutcome::TimeoutKilled) => warn!("{} had to be killed during shutdown", name),
+        Ok(StopOutcome::Failed) => warn!("{} did not exit cleanly during shutdown", name),
+        Ok(_) => {}
+        Err(e) => error!("Failed to stop {}: {}", name, e),
+    }
+    result
+}
+
+fn stopped_message(label: &str, outcome: StopOutcome) -> String {
+    match outcome {
+        StopOutcome::TimeoutKilled => format!("{} killed after timeout", label),
+        StopOutcome::Failed => format!("{} stopped with an error", label),
+        _ => format!("{} stopped", label),
+    }
+}
@@ -0,0 +1,118 @@
+// Status History Module
+// Keeps the events of the current launch or shutdown so a window that loads late can replay them
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+
+use crate::download::DownloadProgress;
+use crate::service::ServiceStatus;
+use crate::StartupStatus;
+
+/// Events kept for replay; enough for a full startup with a few restarts
+const HISTORY_LEN: usize = 200;
+
+/// A startup or shutdown event as it was sent to the UI
+#[derive(Clone, serde::Serialize)]
+pub struct StatusEvent {
+    /// Event name ("startup-status" or "shutdown-status")
+    pub event: &'static str,
+    pub payload: StartupStatus,
+}
+
+/// Everything a window needs to render the current state
+#[derive(Clone, serde::Serialize)]
+pub struct StatusSnapshot {
+    pub services: Vec<ServiceStatus>,
+    /// Services are being launched; the history shows how far it got
+    pub starting: bool,
+    pub shutting_down: bool,
+    pub history: Vec<StatusEvent>,
+    /// Latest progress of the downloads still running
+    pub downloads: Vec<DownloadProgress>,
+}
+
+/// Bounded log of the events sent to the UI, numbered in order
+#[derive(Default)]
+pub struct EventLog {
+    inner: Mutex<(u64, VecDeque<StatusEvent>)>,
+    /// Only the latest progress matters, so it is kept per stage instead of in the history
+    downloads: Mutex<BTreeMap<String, DownloadProgress>>,
+}
+
+impl EventLog {
+    /// Number the payload and remember it; returns it with its sequence number set
+    pub fn record(&self, event: &'static str, mut payload: StartupStatus) -> StartupStatus {
+        let Ok(mut inner) = self.inner.lock() else {
+            return payload;
+        };
+        let (seq, events) = &mut *inner;
+        *seq += 1;
+        payload.seq = *seq;
+
+        if events.len() == HISTORY_LEN {
+            events.pop_front();
+        }
+        events.push_back(StatusEvent { event, payload: payload.clone() });
+        payload
+    }
+
+    /// Forget the events of an earlier start or stop
+    pub fn clear(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.1.clear();
+        }
+        if let Ok(mut downloads) = self.downloads.lock() {
+            downloads.clear();
+        }
+    }
+
+    /// Remember the progress of a download; a finished one is dropped
+    pub fn record_download(&self, progress: &DownloadProgress) {
+        let Ok(mut downloads) = self.downloads.lock() else {
+            return;
+        };
+        if progress.done {
+            downloads.remove(&progress.stage);
+        } else {
+            downloads.insert(progress.stage.clone(), progress.clone());
+        }
+    }
+
+    /// Latest progress of every running download
+    pub fn downloads(&self) -> Vec<DownloadProgress> {
+        self.downloads.lock()
+            .map(|downloads| downloads.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Recorded events, oldest first
+    pub fn history(&self) -> Vec<StatusEvent> {
+        self.inner.lock()
+            .map(|inner| inner.1.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(stage: &str, downloaded: u64, done: bool) -> DownloadProgress {
+        DownloadProgress { stage: stage.to_string(), downloaded, total: Some(100), bytes_per_sec: 0, eta_secs: None, done }
+    }
+
+    #[test]
+    fn keeps_the_latest_progress_of_running_downloads() {
+        let log = EventLog::default();
+        log.record_download(&progress("jre", 10, false));
+        log.record_download(&progress("mariadb", 5, false));
+        log.record_download(&progress("jre", 40, false));
+        let downloads: Vec<(String, u64)> = log.downloads().into_iter().map(|p| (p.stage, p.downloaded)).collect();
+        assert_eq!(downloads, [("jre".to_string(), 40), ("mariadb".to_string(), 5)]);
+
+        log.record_download(&progress("jre", 100, true));
+        assert_eq!(log.downloads().len(), 1);
+        log.clear();
+        assert!(log.downloads().is_empty());
+    }
+}
@@ -9,12 +9,16 @@ use tauri::{
 };
 use tracing::{info, error};
 
+/// Identifier of the menubar icon
+const TRAY_ID: &str = "main";
+
 /// Setup system tray
 pub fn setup(app: &App) -> Result<(), Box<dyn std::error::Error>> {
     // Create menu items
     let open_item = MenuItem::with_id(app, "open", "Open BookLore", true, None::<&str>)?;
     let separator1 = PredefinedMenuItem::separator(app)?;
     let restart_item = MenuItem::with_id(app, "restart", "Restart Services", true, None::<&str>)?;
+    let backup_item = MenuItem::with_id(app, "backup", "Back Up Now", true, None::<&str>)?;
     let separator2 = PredefinedMenuItem::separator(app)?;
     let autostart_item = MenuItem::with_id(app, "autostart", "Launch at Login", true, None::<&str>)?;
     let separator3 = PredefinedMenuItem::separator(app)?;
@@ -26,6 +30,7 @@ pub fn setup(app: &App) -> Result<(), Box<dyn std::error::Error>> {
         &open_item,
         &separator1,
         &restart_item,
+        &backup_item,
         &separator2,
         &autostart_item,
         &separator3,
@@ -35,7 +40,7 @@ pub fn setup(app: &App) -> Result<(), Box<dyn std::error::Error>> {
     
     // Create tray icon
     // Using a simple emoji as fallback - in production, use proper icon
-    let _tray = TrayIconBuilder::new()
+    let _tray = TrayIconBuilder::with_id(TRAY_ID)
         .icon(get_tray_icon(app)?)
         .menu(&menu)
         .show_menu_on_left_click(false)
@@ -62,23 +67,36 @@ fn handle_menu_event(app: &AppHandle, menu_id: &str) {
     match menu_id {
         "open" => {
             // Open in browser
-            // Open in browser
-            let _ = open::that(format!("http://localhost:{}", crate::constants::FRONTEND_PORT));
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let state = app.state::<crate::AppState>();
+                match state.settings().await {
+                    Ok(config) => {
+                        let _ = open::that(format!("http://localhost:{}", config.ports.frontend));
+                    }
+                    Err(e) => error!("Cannot open BookLore: {}", e),
+                }
+            });
         }
         "restart" => {
             // Restart services
             let app = app.clone();
             tauri::async_runtime::spawn(async move {
-                // Get state and restart
                 let state = app.state::<crate::AppState>();
-                if let Err(e) = crate::stop_services(state.clone()).await {
-                    error!("Failed to stop services: {}", e);
-                }
-                if let Err(e) = crate::start_services(app.clone(), state).await {
+                if let Err(e) = crate::restart_services(app.clone(), state).await {
                     error!("Failed to restart services: {}", e);
+                    update_status(&app, "Restart failed");
+                    crate::emit_status(&app, "backend", "error", &format!("Restart failed: {}", e), 100);
                 }
             });
         }
+        "backup" => {
+            // Back up the database; the result is reported as a notification
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let _ = crate::backup::run_backup(&app).await;
+            });
+        }
         "autostart" => {
             // Toggle autostart
             info!("Autostart toggled");
@@ -92,15 +110,9 @@ fn handle_menu_event(app: &AppHandle, menu_id: &str) {
             }
         }
         "quit" => {
-            // Quit application
+            // Quit application through the same ordered shutdown as the app menu
             info!("Quit requested");
-            let app = app.clone();
-            tauri::async_runtime::spawn(async move {
-                // Stop services before quitting
-                let state = app.state::<crate::AppState>();
-                let _ = crate::stop_services(state).await;
-                app.exit(0);
-            });
+            crate::shutdown::request(app);
         }
         _ => {}
     }
@@ -153,10 +165,10 @@ fn get_tray_icon(app: &App) -> Result<Image<'static>, Box<dyn std::error::Error>
     }
 }
 
-/// Update tray icon based on status
-#[allow(dead_code)]
-pub fn update_status(_app: &AppHandle, running: bool) {
-    // Could update icon to show running/stopped status
-    // For now, just log
-    info!("Tray status updated: running={}", running);
+/// Show the current service status in the tray tooltip
+pub fn update_status(app: &AppHandle, status: &str) {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let _ = tray.set_tooltip(Some(format!("BookLore - {}", status)));
+    }
+    info!("Tray status updated: {}", status);
 }
@@ -4,11 +4,21 @@ const { listen } = window.__TAURI__.event;
 
 // Status elements
 const statusMariadb = document.getElementById('status-mariadb');
+const statusRecovery = document.getElementById('status-recovery');
 const statusJre = document.getElementById('status-jre');
 const statusBackend = document.getElementById('status-backend');
 const progressBar = document.getElementById('progress');
 const errorContainer = document.getElementById('error-container');
 const errorText = document.getElementById('error-text');
+const errorLog = document.getElementById('error-log');
+const recoveryActions = document.getElementById('recovery-actions');
+const repairButton = document.getElementById('repair-button');
+const restoreButton = document.getElementById('restore-button');
+const restoreConfirm = document.getElementById('restore-confirm');
+const backupSelect = document.getElementById('backup-select');
+const restoreConfirmButton = document.getElementById('restore-confirm-button');
+const restoreCancelButton = document.getElementById('restore-cancel-button');
+const downloads = document.getElementById('downloads');
 
 // Check if we are in shutdown mode
 const urlParams = new URLSearchParams(window.location.search);
@@ -53,54 +63,239 @@ function setProgress(percent) {
   progressBar.style.width = `${percent}%`;
 }
 
+function formatBytes(bytes) {
+  if (bytes >= 1024 * 1024) {
+    return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+  }
+  return `${Math.round(bytes / 1024)} KB`;
+}
+
+const DOWNLOAD_LABELS = {
+  jre: 'Java runtime',
+  mariadb: 'MariaDB'
+};
+
+// Bar for one stage's download, created the first time that stage reports progress
+function downloadBar(stage) {
+  let item = downloads.querySelector(`[data-stage="${stage}"]`);
+  if (!item) {
+    item = document.createElement('div');
+    item.className = 'download-item';
+    item.dataset.stage = stage;
+    item.innerHTML = `
+      <div class="download-label"></div>
+      <div class="progress-bar download-bar"><div class="progress-fill"></div></div>
+      <p class="download-text"></p>`;
+    item.querySelector('.download-label').textContent = DOWNLOAD_LABELS[stage] || stage;
+    downloads.appendChild(item);
+  }
+  return item;
+}
+
+// Byte-level progress of a runtime download; the JRE and MariaDB each get their own bar
+function showDownloadProgress({ stage, downloaded, total, bytes_per_sec, eta_secs, done }) {
+  if (done) {
+    downloads.querySelector(`[data-stage="${stage}"]`)?.remove();
+    return;
+  }
+  const item = downloadBar(stage);
+
+  const parts = [total ? `${formatBytes(downloaded)} of ${formatBytes(total)}` : formatBytes(downloaded)];
+  if (bytes_per_sec > 0) {
+    parts.push(`${formatBytes(bytes_per_sec)}/s`);
+  }
+  if (eta_secs !== null && eta_secs !== undefined) {
+    parts.push(eta_secs >= 60 ? `${Math.ceil(eta_secs / 60)} min left` : `${eta_secs} s left`);
+  }
+  item.querySelector('.download-text').textContent = parts.join(' · ');
+  item.querySelector('.progress-fill').style.width = total ? `${Math.min(100, (downloaded / total) * 100)}%` : '100%';
+}
+
 function showError(message) {
+  downloads.replaceChildren();
   errorText.textContent = message;
   errorContainer.classList.add('visible');
 }
 
-// Listen for status updates from Tauri backend
-async function initializeApp() {
+// Show the tail of the backend output below the error message
+async function showBackendLogs() {
   try {
-    // Listen for startup events from Rust backend
-    await listen('startup-status', (event) => {
-      const { stage, status, message, progress } = event.payload;
-
-      let element;
-      switch (stage) {
-        case 'mariadb':
-          element = statusMariadb;
-          break;
-        case 'jre':
-          element = statusJre;
-          break;
-        case 'backend':
-          element = statusBackend;
-          break;
-      }
+    const lines = await invoke('get_backend_logs', { lines: 50 });
+    if (lines.length > 0) {
+      errorLog.textContent = lines.join('\n');
+      errorLog.classList.add('visible');
+      errorLog.scrollTop = errorLog.scrollHeight;
+    }
+  } catch (e) {
+    console.error('Failed to load backend logs:', e);
+  }
+}
 
-      if (element) {
-        setStatus(element, status, message);
-      }
+// Offer repair or restore when the crash check found damaged tables
+function showRecoveryActions() {
+  recoveryActions.classList.remove('hidden');
+}
 
-      if (progress !== undefined) {
-        setProgress(progress);
-      }
+async function runRecovery(command, args) {
+  recoveryActions.classList.add('hidden');
+  restoreConfirm.classList.add('hidden');
+  errorContainer.classList.remove('visible');
+  try {
+    await invoke(command, args);
+  } catch (e) {
+    console.error(`${command} failed:`, e);
+    showError(`${e}`);
+  }
+}
 
-      // If backend is complete, navigate webview to BookLore UI
-      // Uses the frontend HTTP server (port 18088) which serves Angular and proxies /api to backend
-      if (stage === 'backend' && status === STATUS.COMPLETE) {
-        console.log('All services ready! Navigating to BookLore UI...');
-        setTimeout(() => {
-          // Navigate the current webview to the frontend server
-          window.location.href = 'http://localhost:18088';
-        }, 1000);
-      }
+repairButton.addEventListener('click', () => runRecovery('repair_database'));
 
-      // Handle errors
-      if (status === STATUS.ERROR) {
-        showError(message);
-      }
-    });
+// "booklore-20250101-030000.sql.gz" -> "2025-01-01 03:00"
+function describeBackup(name) {
+  const match = name.match(/^booklore-(\d{4})(\d{2})(\d{2})-(\d{2})(\d{2})/);
+  return match ? `${match[1]}-${match[2]}-${match[3]} ${match[4]}:${match[5]}` : name;
+}
+
+// Let the user pick a backup and confirm before anything is replaced
+restoreButton.addEventListener('click', async () => {
+  let backups;
+  try {
+    backups = await invoke('list_backups');
+  } catch (e) {
+    console.error('Failed to list backups:', e);
+    showError(`${e}`);
+    return;
+  }
+  if (backups.length === 0) {
+    showError('No backups found. Repair the tables or restore a backup manually.');
+    showRecoveryActions();
+    return;
+  }
+  backupSelect.replaceChildren(...backups.map((name, index) => {
+    const option = document.createElement('option');
+    option.value = name;
+    option.textContent = describeBackup(name) + (index === 0 ? ' (latest)' : '');
+    return option;
+  }));
+  restoreConfirm.classList.remove('hidden');
+});
+
+restoreConfirmButton.addEventListener('click', () => runRecovery('restore_backup', { name: backupSelect.value }));
+
+restoreCancelButton.addEventListener('click', () => restoreConfirm.classList.add('hidden'));
+
+// Sequence numbers of the status events already shown, so replayed and live events are applied once
+const seenEvents = new Set();
+
+function alreadySeen(payload) {
+  if (payload.seq === undefined) {
+    return false;
+  }
+  if (seenEvents.has(payload.seq)) {
+    return true;
+  }
+  seenEvents.add(payload.seq);
+  return false;
+}
+
+async function handleStartupStatus(payload) {
+  if (alreadySeen(payload)) {
+    return;
+  }
+  const { stage, status, message, progress } = payload;
+
+  let element;
+  switch (stage) {
+    case 'mariadb':
+      element = statusMariadb;
+      break;
+    case 'recovery':
+      element = statusRecovery;
+      statusRecovery.classList.remove('hidden');
+      break;
+    case 'jre':
+      element = statusJre;
+      break;
+    case 'backend':
+      element = statusBackend;
+      break;
+  }
+
+  if (element) {
+    setStatus(element, status, message);
+  }
+
+  if (progress !== undefined) {
+    setProgress(progress);
+  }
+
+  // If backend is complete, navigate webview to BookLore UI
+  // Uses the frontend HTTP server which serves Angular and proxies /api to backend
+  // (its port may have moved if the default one was taken)
+  if (stage === 'backend' && status === STATUS.COMPLETE) {
+    console.log('All services ready! Navigating to BookLore UI...');
+    const url = await invoke('get_ui_url');
+    setTimeout(() => {
+      // Navigate the current webview to the frontend server
+      window.location.href = url;
+    }, 1000);
+  }
+
+  // Handle errors
+  if (status === STATUS.ERROR) {
+    showError(message);
+    if (stage === 'backend') {
+      showBackendLogs();
+    }
+    if (stage === 'recovery') {
+      showRecoveryActions();
+    }
+  }
+}
+
+function handleShutdownStatus(payload) {
+  if (alreadySeen(payload)) {
+    return;
+  }
+  const { stage, status, message, progress } = payload;
+
+  let element;
+  switch (stage) {
+    case 'backend':
+      element = statusBackend;
+      break;
+    case 'jre': // We might not need to stop JRE explicitly if it's a child process, but nice to show
+      element = statusJre;
+      break;
+    case 'mariadb':
+      element = statusMariadb;
+      break;
+  }
+
+  if (element) {
+    setStatus(element, status, message);
+  }
+  if (progress !== undefined) {
+    setProgress(progress);
+  }
+}
+
+// Switch the loader page to the shutdown view
+function showShutdown() {
+  document.querySelector('h1').textContent = 'Stopping BookLore...';
+  setStatus(statusBackend, STATUS.ACTIVE, 'Stopping backend...');
+  setStatus(statusJre, STATUS.PENDING, 'Waiting...');
+  setStatus(statusMariadb, STATUS.PENDING, 'Waiting...');
+  setProgress(0);
+  errorContainer.classList.remove('visible');
+}
+
+// Listen for status updates from Tauri backend
+async function initializeApp() {
+  try {
+    // Listen for startup events from Rust backend
+    await listen('startup-status', (event) => handleStartupStatus(event.payload));
+    await listen('download-progress', (event) => showDownloadProgress(event.payload));
 
     // Listen for file drops directly on the webview
     await listen('tauri://drop', async (event) => {
@@ -122,52 +317,32 @@ async function initializeApp() {
     // Listen for shutdown events
     await listen('shutdown-start', () => {
       console.log('Shutdown sequence initiated');
-      document.querySelector('h1').textContent = 'Stopping BookLore...';
-
-      // Reset statuses
-      setStatus(statusBackend, STATUS.ACTIVE, 'Stopping backend...');
-      setStatus(statusJre, STATUS.PENDING, 'Waiting...');
-      setStatus(statusMariadb, STATUS.PENDING, 'Waiting...');
-      setProgress(0);
-      errorContainer.classList.remove('visible');
+      showShutdown();
     });
 
-    await listen('shutdown-status', (event) => {
-      const { stage, status, message, progress } = event.payload;
-
-      let element;
-      switch (stage) {
-        case 'backend':
-          element = statusBackend;
-          break;
-        case 'jre': // We might not need to stop JRE explicitly if it's a child process, but nice to show
-          element = statusJre;
-          break;
-        case 'mariadb':
-          element = statusMariadb;
-          break;
-      }
+    await listen('shutdown-status', (event) => handleShutdownStatus(event.payload));
 
-      if (element) {
-        setStatus(element, status, message);
+    // Catch up on whatever happened before this window started listening
+    const snapshot = await invoke('get_status');
+    if (snapshot.shutting_down || isShutdown) {
+      // The window may reload during shutdown if we navigate back to it
+      console.log('Shutdown mode detected');
+      showShutdown();
+      for (const { event, payload } of snapshot.history) {
+        if (event === 'shutdown-status') {
+          handleShutdownStatus(payload);
+        }
       }
-      if (progress !== undefined) {
-        setProgress(progress);
+    } else if (snapshot.starting) {
+      // A start is already under way; show how far it got and follow along
+      for (const { event, payload } of snapshot.history) {
+        if (event === 'startup-status') {
+          await handleStartupStatus(payload);
+        }
       }
-    });
-
-    // Start the initialization process ONLY if we are not shutting down
-    // (The window might reload during shutdown if we navigate back to it)
-    if (!isShutdown) {
-      await invoke('start_services');
+      snapshot.downloads.forEach(showDownloadProgress);
     } else {
-      // Initialize UI for shutdown immediately
-      console.log('Shutdown mode detected');
-      document.querySelector('h1').textContent = 'Stopping BookLore...';
-      setStatus(statusBackend, STATUS.ACTIVE, 'Stopping backend...');
-      setStatus(statusJre, STATUS.PENDING, 'Waiting...');
-      setStatus(statusMariadb, STATUS.PENDING, 'Waiting...');
-      setProgress(0);
+      await invoke('start_services');
     }
   } catch (error) {
     console.error('Failed to initialize:', error);
@@ -0,0 +1,93 @@
+// Settings window for the runner configuration (config/runner.toml)
+const { invoke } = window.__TAURI__.core;
+const { getCurrentWindow } = window.__TAURI__.window;
+
+const form = document.getElementById('settings-form');
+const messageBox = document.getElementById('message');
+
+// Form field id -> [section, key, type] in the config
+const FIELDS = {
+  'ports-frontend': ['ports', 'frontend', Number],
+  'ports-backend': ['ports', 'backend', Number],
+  'ports-mariadb': ['ports', 'mariadb', Number],
+  'backend-heap-max': ['backend', 'heap_max_mb', Number],
+  'backend-heap-min': ['backend', 'heap_min_mb', Number],
+  'backend-stop-timeout': ['backend', 'stop_timeout_secs', Number],
+  'mariadb-version': ['mariadb', 'version', String],
+  'mariadb-socket-only': ['mariadb', 'socket_only', Boolean],
+  'mariadb-stop-timeout': ['mariadb', 'stop_timeout_secs', Number],
+  'external-enabled': ['external_database', 'enabled', Boolean],
+  'external-host': ['external_database', 'host', String],
+  'external-port': ['external_database', 'port', Number],
+  'external-user': ['external_database', 'user', String],
+  'external-password': ['external_database', 'password', String],
+  'external-schema': ['external_database', 'schema', String],
+  'paths-data': ['paths', 'data_dir', String],
+  'paths-books': ['paths', 'books_dir', String],
+  'paths-bookdrop': ['paths', 'bookdrop_dir', String],
+  'paths-backups': ['paths', 'backups_dir', String],
+  'backup-enabled': ['backup', 'enabled', Boolean],
+  'backup-interval': ['backup', 'interval_hours', Number],
+  'backup-keep-daily': ['backup', 'keep_daily', Number],
+  'backup-keep-weekly': ['backup', 'keep_weekly', Number],
+  'downloads-adoptium': ['downloads', 'adoptium_url', String],
+  'downloads-adoptium-binary': ['downloads', 'adoptium_binary_url', String],
+  'downloads-mariadb': ['downloads', 'mariadb_url', String],
+  'downloads-offline': ['downloads', 'offline_dir', String],
+};
+
+let config = null;
+
+function showMessage(text, kind) {
+  messageBox.textContent = text;
+  messageBox.className = `message visible ${kind}`;
+}
+
+async function loadSettings() {
+  try {
+    config = await invoke('get_config');
+    for (const [id, [section, key, type]] of Object.entries(FIELDS)) {
+      const input = document.getElementById(id);
+      if (type === Boolean) {
+        input.checked = config[section][key];
+      } else {
+        input.value = config[section][key];
+      }
+    }
+  } catch (e) {
+    showMessage(`Failed to load settings: ${e}`, 'error');
+  }
+}
+
+form.addEventListener('submit', async (event) => {
+  event.preventDefault();
+  if (!config) {
+    return;
+  }
+
+  // Start from the loaded config so settings without a form field are kept
+  const updated = structuredClone(config);
+  for (const [id, [section, key, type]] of Object.entries(FIELDS)) {
+    const input = document.getElementById(id);
+    if (type === Boolean) {
+      updated[section][key] = input.checked;
+    } else {
+      // Passwords may legitimately start or end with spaces
+      updated[section][key] = type(input.type === 'password' ? input.value : input.value.trim());
+    }
+  }
+
+  try {
+    await invoke('set_config', { config: updated });
+    config = updated;
+    showMessage('Settings saved. Use "Restart Services" from the menubar icon to apply them.', 'success');
+  } catch (e) {
+    showMessage(`${e}`, 'error');
+  }
+});
+
+document.getElementById('cancel').addEventListener('click', () => {
+  getCurrentWindow().close();
+});
+
+loadSettings();